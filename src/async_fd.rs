@@ -0,0 +1,42 @@
+//! Readiness notifications for file descriptors, shared by the async pin implementations
+//!
+//! With the `async-io` feature the [`async-io`] reactor is used, which runs on its own thread
+//! and works with any executor, including tokio. Otherwise the tokio reactor of the current
+//! runtime is used.
+//!
+//! [`async-io`]: https://docs.rs/async-io
+
+use std::io;
+use std::os::unix::io::BorrowedFd;
+
+/// A file descriptor registered with the async reactor
+///
+/// The file descriptor is used as is, without being switched to non-blocking mode.
+pub(crate) struct AsyncFd<'a> {
+    #[cfg(feature = "async-io")]
+    inner: async_io::Async<BorrowedFd<'a>>,
+    #[cfg(not(feature = "async-io"))]
+    inner: tokio::io::unix::AsyncFd<BorrowedFd<'a>>,
+}
+
+impl<'a> AsyncFd<'a> {
+    /// Registers `fd` with the reactor.
+    pub(crate) fn new(fd: BorrowedFd<'a>) -> io::Result<Self> {
+        #[cfg(feature = "async-io")]
+        let inner = async_io::Async::new_nonblocking(fd)?;
+        #[cfg(not(feature = "async-io"))]
+        let inner = tokio::io::unix::AsyncFd::new(fd)?;
+        Ok(AsyncFd { inner })
+    }
+
+    /// Waits until the file descriptor is reported readable.
+    ///
+    /// Readiness may be spurious, callers must check that data is actually available.
+    pub(crate) async fn readable(&self) -> io::Result<()> {
+        #[cfg(feature = "async-io")]
+        self.inner.readable().await?;
+        #[cfg(not(feature = "async-io"))]
+        self.inner.readable().await?.clear_ready();
+        Ok(())
+    }
+}
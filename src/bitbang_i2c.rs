@@ -0,0 +1,666 @@
+//! Implementation of [`embedded-hal`] I2C traits by bit-banging GPIO pins
+//!
+//! [`embedded-hal`]: https://docs.rs/embedded-hal
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use embedded_hal::digital::{InputPin, OutputPin, PinState};
+use embedded_hal::i2c::{NoAcknowledgeSource, Operation as I2cOperation, SevenBitAddress};
+
+use crate::delay::spin_until;
+
+/// I2C master bit-banged over two open-drain GPIO pins
+///
+/// Both pins must be configured as open-drain outputs that can be read back, with pull-up
+/// resistors on the lines, such as [`CdevPin`]s built with
+/// [`Drive::OpenDrain`](https://docs.rs/gpiocdev/0.8.0/gpiocdev/line/enum.Drive.html).
+/// Setting a pin high releases the line and setting it low pulls it down.
+///
+/// Clock stretching by devices is supported: the master waits for SCL to actually rise before
+/// carrying on, up to a configurable timeout. Bit timing uses busy waiting, so the thread keeps
+/// a CPU busy for the duration of transactions, and the actual bit rate ends up below the
+/// configured one on slow or loaded systems.
+///
+/// ```no_run
+/// use embedded_hal::digital::PinState;
+/// use embedded_hal::i2c::I2c;
+/// use linux_embedded_hal::gpiocdev::line::Drive;
+/// use linux_embedded_hal::{BitbangI2c, CdevPin};
+///
+/// let open_drain = |line| {
+///     CdevPin::builder("/dev/gpiochip0", line)
+///         .output(PinState::High)
+///         .drive(Drive::OpenDrain)
+///         .build()
+/// };
+/// let mut i2c = BitbangI2c::new(open_drain(2)?, open_drain(3)?, 100_000)?;
+/// let mut temperature = [0; 2];
+/// i2c.write_read(0x48, &[0x00], &mut temperature)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`CdevPin`]: crate::CdevPin
+pub struct BitbangI2c<SDA, SCL> {
+    sda: SDA,
+    scl: SCL,
+    half_period: Duration,
+    stretch_timeout: Duration,
+}
+
+impl<SDA, SCL, E> BitbangI2c<SDA, SCL>
+where
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+{
+    /// Default maximum time a device may hold SCL low, as for SMBus
+    pub const DEFAULT_STRETCH_TIMEOUT: Duration = Duration::from_millis(25);
+
+    /// Creates a bus on the `sda` and `scl` pins clocked at `frequency` Hz.
+    ///
+    /// Returns [`BitbangI2cError::InvalidFrequency`] if `frequency` is zero.
+    pub fn new(sda: SDA, scl: SCL, frequency: u32) -> Result<Self, BitbangI2cError<E>> {
+        Ok(BitbangI2c {
+            sda,
+            scl,
+            half_period: half_period(frequency)?,
+            stretch_timeout: Self::DEFAULT_STRETCH_TIMEOUT,
+        })
+    }
+
+    /// Changes the bus clock frequency, in Hz.
+    ///
+    /// Returns [`BitbangI2cError::InvalidFrequency`] if `frequency` is zero, leaving the
+    /// frequency unchanged.
+    pub fn set_frequency(&mut self, frequency: u32) -> Result<(), BitbangI2cError<E>> {
+        self.half_period = half_period(frequency)?;
+        Ok(())
+    }
+
+    /// Changes how long devices may stretch the clock before a transaction fails with
+    /// [`BitbangI2cError::ClockStretchTimeout`].
+    pub fn set_clock_stretch_timeout(&mut self, timeout: Duration) {
+        self.stretch_timeout = timeout;
+    }
+
+    /// Releases the SDA and SCL pins.
+    pub fn release(self) -> (SDA, SCL) {
+        (self.sda, self.scl)
+    }
+
+    fn wait(&self) {
+        spin_until(Instant::now() + self.half_period);
+    }
+
+    /// Releases SCL and waits for it to rise, as devices may hold it low.
+    fn release_scl(&mut self) -> Result<(), BitbangI2cError<E>> {
+        self.scl.set_high()?;
+        let deadline = Instant::now() + self.stretch_timeout;
+        while self.scl.is_low()? {
+            if Instant::now() > deadline {
+                return Err(BitbangI2cError::ClockStretchTimeout);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a start condition, or a repeated start within a transaction.
+    fn start(&mut self) -> Result<(), BitbangI2cError<E>> {
+        self.sda.set_high()?;
+        self.wait();
+        self.release_scl()?;
+        if self.sda.is_low()? {
+            return Err(BitbangI2cError::ArbitrationLoss);
+        }
+        self.wait();
+        self.sda.set_low()?;
+        self.wait();
+        self.scl.set_low()?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), BitbangI2cError<E>> {
+        self.sda.set_low()?;
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        self.sda.set_high()?;
+        self.wait();
+        if self.sda.is_low()? {
+            return Err(BitbangI2cError::ArbitrationLoss);
+        }
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), BitbangI2cError<E>> {
+        self.sda.set_state(PinState::from(bit))?;
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        if bit && self.sda.is_low()? {
+            return Err(BitbangI2cError::ArbitrationLoss);
+        }
+        self.scl.set_low()?;
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool, BitbangI2cError<E>> {
+        self.sda.set_high()?;
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        let bit = self.sda.is_high()?;
+        self.scl.set_low()?;
+        Ok(bit)
+    }
+
+    /// Writes `byte` MSB first and returns whether it was acknowledged.
+    fn write_byte(&mut self, byte: u8) -> Result<bool, BitbangI2cError<E>> {
+        for i in (0..8).rev() {
+            self.write_bit(byte >> i & 1 == 1)?;
+        }
+        Ok(!self.read_bit()?)
+    }
+
+    /// Reads a byte MSB first and acknowledges it if `ack` is true.
+    fn read_byte(&mut self, ack: bool) -> Result<u8, BitbangI2cError<E>> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = byte << 1 | u8::from(self.read_bit()?);
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    fn run(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [I2cOperation],
+    ) -> Result<(), BitbangI2cError<E>> {
+        let mut previous_is_read = None;
+        for i in 0..operations.len() {
+            let (done, rest) = operations.split_at_mut(i + 1);
+            let operation = &mut done[i];
+            let is_read = matches!(operation, I2cOperation::Read(_));
+
+            // Adjacent operations of the same kind are merged without a repeated start.
+            if previous_is_read != Some(is_read) {
+                self.start()?;
+                if !self.write_byte(address << 1 | u8::from(is_read))? {
+                    return Err(BitbangI2cError::NoAcknowledge(NoAcknowledgeSource::Address));
+                }
+            }
+            previous_is_read = Some(is_read);
+
+            match operation {
+                I2cOperation::Write(bytes) => {
+                    for byte in bytes.iter() {
+                        if !self.write_byte(*byte)? {
+                            return Err(BitbangI2cError::NoAcknowledge(NoAcknowledgeSource::Data));
+                        }
+                    }
+                }
+                I2cOperation::Read(buffer) => {
+                    // The last byte read before a write or the stop must not be acknowledged.
+                    let ends_reads = rest
+                        .iter()
+                        .take_while(|op| matches!(op, I2cOperation::Read(_)))
+                        .all(|op| matches!(op, I2cOperation::Read(buf) if buf.is_empty()));
+                    let len = buffer.len();
+                    for (j, byte) in buffer.iter_mut().enumerate() {
+                        *byte = self.read_byte(!(ends_reads && j + 1 == len))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn half_period<E>(frequency: u32) -> Result<Duration, BitbangI2cError<E>> {
+    if frequency == 0 {
+        return Err(BitbangI2cError::InvalidFrequency);
+    }
+    Ok(Duration::from_secs(1) / frequency / 2)
+}
+
+/// Error type for [`BitbangI2c`], implementing [`embedded_hal::i2c::Error`]
+#[derive(Debug)]
+pub enum BitbangI2cError<E> {
+    /// An error accessing one of the pins
+    Pin(E),
+    /// The device did not acknowledge its address or a byte of data
+    NoAcknowledge(NoAcknowledgeSource),
+    /// Another master drove SDA low while this one released it
+    ArbitrationLoss,
+    /// SCL was held low for longer than the clock stretching timeout
+    ClockStretchTimeout,
+    /// The bus clock frequency was zero
+    InvalidFrequency,
+}
+
+impl<E> From<E> for BitbangI2cError<E> {
+    fn from(err: E) -> Self {
+        BitbangI2cError::Pin(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for BitbangI2cError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitbangI2cError::Pin(err) => write!(f, "{}", err),
+            BitbangI2cError::NoAcknowledge(source) => write!(f, "{}", source),
+            BitbangI2cError::ArbitrationLoss => write!(f, "Arbitration lost"),
+            BitbangI2cError::ClockStretchTimeout => write!(f, "Clock stretching timed out"),
+            BitbangI2cError::InvalidFrequency => write!(f, "I2C frequency must not be zero"),
+        }
+    }
+}
+
+impl<E> std::error::Error for BitbangI2cError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BitbangI2cError::Pin(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<E: fmt::Debug> embedded_hal::i2c::Error for BitbangI2cError<E> {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        use embedded_hal::i2c::ErrorKind;
+
+        match self {
+            BitbangI2cError::Pin(_) => ErrorKind::Other,
+            BitbangI2cError::NoAcknowledge(source) => ErrorKind::NoAcknowledge(*source),
+            BitbangI2cError::ArbitrationLoss => ErrorKind::ArbitrationLoss,
+            BitbangI2cError::ClockStretchTimeout => ErrorKind::Bus,
+            BitbangI2cError::InvalidFrequency => ErrorKind::Other,
+        }
+    }
+}
+
+impl<SDA, SCL, E> embedded_hal::i2c::ErrorType for BitbangI2c<SDA, SCL>
+where
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    E: fmt::Debug,
+{
+    type Error = BitbangI2cError<E>;
+}
+
+impl<SDA, SCL, E> embedded_hal::i2c::I2c<SevenBitAddress> for BitbangI2c<SDA, SCL>
+where
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    E: fmt::Debug,
+{
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [I2cOperation],
+    ) -> Result<(), Self::Error> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.run(address, operations);
+        // Always try to leave the bus idle, but report the first error.
+        let stop = self.stop();
+        result.and(stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::digital::ErrorType;
+    use embedded_hal::i2c::I2c;
+    use std::cell::RefCell;
+    use std::convert::Infallible;
+    use std::rc::Rc;
+
+    /// Open-drain SDA and SCL lines with pull-ups, read back as low if anyone pulls them down
+    #[derive(Default)]
+    struct Bus {
+        sda_pulled_low: u8,
+        scl_pulled_low: u8,
+        scl_stuck_low: bool,
+        device: Option<Device>,
+    }
+
+    impl Bus {
+        fn levels(&self) -> (bool, bool) {
+            let device_sda_low = matches!(&self.device, Some(device) if device.sda_low);
+            (
+                self.sda_pulled_low == 0 && !device_sda_low,
+                self.scl_pulled_low == 0 && !self.scl_stuck_low,
+            )
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Start,
+        Address(u8),
+        Write(u8),
+        /// A byte sent by the device, and whether the master acknowledged it
+        Read(u8, bool),
+        Stop,
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Idle,
+        Address,
+        Write,
+        Read,
+    }
+
+    /// A device acknowledging every byte written to it and answering reads with consecutive
+    /// bytes starting at `next_read`
+    struct Device {
+        address: u8,
+        next_read: u8,
+        events: Vec<Event>,
+        state: State,
+        /// Clock pulses seen in the current byte, the ninth being the acknowledge bit
+        clock: u8,
+        byte: u8,
+        sda_low: bool,
+    }
+
+    impl Device {
+        fn new(address: u8) -> Self {
+            Device {
+                address,
+                next_read: 0xC0,
+                events: Vec::new(),
+                state: State::Idle,
+                clock: 0,
+                byte: 0,
+                sda_low: false,
+            }
+        }
+
+        fn update(&mut self, (sda_was, scl_was): (bool, bool), (sda, scl): (bool, bool)) {
+            match (scl_was, scl) {
+                (true, true) if sda_was && !sda => self.start(),
+                (true, true) if !sda_was && sda => {
+                    self.events.push(Event::Stop);
+                    self.state = State::Idle;
+                    self.sda_low = false;
+                }
+                (false, true) => self.rise(sda),
+                (true, false) => self.fall(),
+                _ => {}
+            }
+        }
+
+        fn start(&mut self) {
+            self.events.push(Event::Start);
+            self.state = State::Address;
+            self.clock = 0;
+            self.byte = 0;
+            self.sda_low = false;
+        }
+
+        fn rise(&mut self, sda: bool) {
+            match self.state {
+                State::Address | State::Write if self.clock < 8 => {
+                    self.byte = self.byte << 1 | u8::from(sda);
+                }
+                State::Read if self.clock == 8 => {
+                    self.events.push(Event::Read(self.byte, !sda));
+                    if sda {
+                        self.state = State::Idle;
+                    }
+                }
+                _ => {}
+            }
+            self.clock += 1;
+        }
+
+        fn fall(&mut self) {
+            match (self.state, self.clock) {
+                (State::Address, 8) => {
+                    if self.byte >> 1 == self.address {
+                        self.events.push(Event::Address(self.byte));
+                        self.sda_low = true;
+                    } else {
+                        self.state = State::Idle;
+                    }
+                }
+                (State::Write, 8) => {
+                    self.events.push(Event::Write(self.byte));
+                    self.sda_low = true;
+                }
+                (State::Read, 8) => self.sda_low = false,
+                (_, 9) => {
+                    self.clock = 0;
+                    if self.state == State::Address {
+                        self.state = if self.byte & 1 == 1 {
+                            State::Read
+                        } else {
+                            State::Write
+                        };
+                    }
+                    self.sda_low = false;
+                    self.byte = 0;
+                    if self.state == State::Read {
+                        self.byte = self.next_read;
+                        self.next_read += 1;
+                        self.drive_bit();
+                    }
+                }
+                (State::Read, _) => self.drive_bit(),
+                _ => {}
+            }
+        }
+
+        fn drive_bit(&mut self) {
+            self.sda_low = self.byte >> (7 - self.clock) & 1 == 0;
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    enum Line {
+        Sda,
+        Scl,
+    }
+
+    struct Pin {
+        bus: Rc<RefCell<Bus>>,
+        line: Line,
+        low: bool,
+    }
+
+    impl Pin {
+        fn set_level(&mut self, low: bool) {
+            if self.low == low {
+                return;
+            }
+            self.low = low;
+            let mut bus = self.bus.borrow_mut();
+            let before = bus.levels();
+            let pulled_low = match self.line {
+                Line::Sda => &mut bus.sda_pulled_low,
+                Line::Scl => &mut bus.scl_pulled_low,
+            };
+            if low {
+                *pulled_low += 1;
+            } else {
+                *pulled_low -= 1;
+            }
+            let after = bus.levels();
+            if let Some(device) = &mut bus.device {
+                device.update(before, after);
+            }
+        }
+    }
+
+    impl ErrorType for Pin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for Pin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.set_level(true);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set_level(false);
+            Ok(())
+        }
+    }
+
+    impl InputPin for Pin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            let (sda, scl) = self.bus.borrow().levels();
+            Ok(match self.line {
+                Line::Sda => sda,
+                Line::Scl => scl,
+            })
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            self.is_high().map(|val| !val)
+        }
+    }
+
+    fn i2c(device: Option<Device>) -> (BitbangI2c<Pin, Pin>, Rc<RefCell<Bus>>) {
+        let bus = Rc::new(RefCell::new(Bus {
+            device,
+            ..Bus::default()
+        }));
+        let pin = |line| Pin {
+            bus: Rc::clone(&bus),
+            line,
+            low: false,
+        };
+        let i2c = BitbangI2c::new(pin(Line::Sda), pin(Line::Scl), 1_000_000).unwrap();
+        (i2c, bus)
+    }
+
+    fn events(bus: &Rc<RefCell<Bus>>) -> Vec<Event> {
+        let mut bus = bus.borrow_mut();
+        std::mem::take(&mut bus.device.as_mut().unwrap().events)
+    }
+
+    #[test]
+    fn write_read_uses_repeated_start() {
+        let (mut i2c, bus) = i2c(Some(Device::new(0x48)));
+        let mut read = [0; 2];
+        i2c.write_read(0x48, &[0x01, 0x80], &mut read).unwrap();
+        assert_eq!(read, [0xC0, 0xC1]);
+        assert_eq!(
+            events(&bus),
+            [
+                Event::Start,
+                Event::Address(0x90),
+                Event::Write(0x01),
+                Event::Write(0x80),
+                Event::Start,
+                Event::Address(0x91),
+                Event::Read(0xC0, true),
+                Event::Read(0xC1, false),
+                Event::Stop,
+            ]
+        );
+        // The bus is left idle.
+        assert_eq!(bus.borrow().levels(), (true, true));
+    }
+
+    #[test]
+    fn last_byte_read_before_write_or_stop_is_not_acknowledged() {
+        let (mut i2c, bus) = i2c(Some(Device::new(0x48)));
+        let mut first = [0; 2];
+        let mut empty = [0; 0];
+        i2c.transaction(
+            0x48,
+            &mut [
+                I2cOperation::Read(&mut first),
+                I2cOperation::Read(&mut empty),
+                I2cOperation::Write(&[0x55]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(first, [0xC0, 0xC1]);
+        assert_eq!(
+            events(&bus),
+            [
+                Event::Start,
+                Event::Address(0x91),
+                Event::Read(0xC0, true),
+                Event::Read(0xC1, false),
+                Event::Start,
+                Event::Address(0x90),
+                Event::Write(0x55),
+                Event::Stop,
+            ]
+        );
+
+        // Adjacent reads are merged, only the very last byte is not acknowledged.
+        let (mut first, mut second) = ([0; 1], [0; 1]);
+        i2c.transaction(
+            0x48,
+            &mut [
+                I2cOperation::Read(&mut first),
+                I2cOperation::Read(&mut second),
+            ],
+        )
+        .unwrap();
+        assert_eq!((first, second), ([0xC2], [0xC3]));
+        assert_eq!(
+            events(&bus),
+            [
+                Event::Start,
+                Event::Address(0x91),
+                Event::Read(0xC2, true),
+                Event::Read(0xC3, false),
+                Event::Stop,
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_device_does_not_acknowledge_address() {
+        let (mut i2c, bus) = i2c(Some(Device::new(0x50)));
+        let err = i2c.write(0x48, &[0x00]).unwrap_err();
+        assert!(matches!(
+            err,
+            BitbangI2cError::NoAcknowledge(NoAcknowledgeSource::Address)
+        ));
+        assert_eq!(events(&bus), [Event::Start, Event::Stop]);
+        // The bus is left idle.
+        assert_eq!(bus.borrow().levels(), (true, true));
+    }
+
+    #[test]
+    fn clock_held_low_times_out() {
+        let (mut i2c, bus) = i2c(None);
+        bus.borrow_mut().scl_stuck_low = true;
+        i2c.set_clock_stretch_timeout(Duration::from_millis(1));
+        let err = i2c.write(0x48, &[0x00]).unwrap_err();
+        assert!(matches!(err, BitbangI2cError::ClockStretchTimeout));
+    }
+
+    #[test]
+    fn zero_frequency_is_rejected() {
+        let (mut i2c, _) = i2c(None);
+        assert!(matches!(
+            i2c.set_frequency(0),
+            Err(BitbangI2cError::InvalidFrequency)
+        ));
+        assert_eq!(i2c.half_period, Duration::from_micros(1) / 2);
+        let (sda, scl) = i2c.release();
+        assert!(matches!(
+            BitbangI2c::new(sda, scl, 0),
+            Err(BitbangI2cError::InvalidFrequency)
+        ));
+    }
+}
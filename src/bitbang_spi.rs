@@ -0,0 +1,439 @@
+//! Implementation of [`embedded-hal`] SPI traits by bit-banging GPIO pins
+//!
+//! [`embedded-hal`]: https://docs.rs/embedded-hal
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use embedded_hal::digital::{InputPin, OutputPin, PinState};
+use embedded_hal::spi::{Mode, Phase, Polarity};
+
+use crate::delay::spin_until;
+
+/// Order in which the bits of each word are shifted out and in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BitOrder {
+    /// Most significant bit first, as used by most devices
+    MsbFirst,
+    /// Least significant bit first
+    LsbFirst,
+}
+
+/// SPI bus bit-banged over GPIO pins
+///
+/// Works with any pins implementing the `embedded-hal` digital traits, such as
+/// [`CdevPin`] or [`SysfsPin`]. All four SPI modes and both bit orders are supported. Chip
+/// select is not handled by the bus, use [`embedded_hal_bus`] or drive it directly.
+///
+/// Bit timing uses busy waiting, so the thread keeps a CPU busy for the duration of transfers,
+/// and the actual bit rate ends up below the configured one on slow or loaded systems, in
+/// particular with [`SysfsPin`].
+///
+/// ```no_run
+/// use embedded_hal::digital::PinState;
+/// use embedded_hal::spi::{SpiBus, MODE_0};
+/// use linux_embedded_hal::{BitOrder, BitbangSpi, CdevPin};
+///
+/// let sck = CdevPin::builder("/dev/gpiochip0", 11).output(PinState::Low).build()?;
+/// let mosi = CdevPin::builder("/dev/gpiochip0", 10).output(PinState::Low).build()?;
+/// let miso = CdevPin::new_input("/dev/gpiochip0", 9, None)?;
+/// let mut spi = BitbangSpi::new(sck, mosi, miso, MODE_0, BitOrder::MsbFirst, 500_000)?;
+/// let mut id = [0x9f, 0, 0, 0];
+/// spi.transfer_in_place(&mut id)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`CdevPin`]: crate::CdevPin
+/// [`SysfsPin`]: crate::SysfsPin
+/// [`embedded_hal_bus`]: https://docs.rs/embedded-hal-bus
+pub struct BitbangSpi<SCK, MOSI, MISO> {
+    sck: SCK,
+    mosi: MOSI,
+    miso: MISO,
+    mode: Mode,
+    bit_order: BitOrder,
+    half_period: Duration,
+}
+
+impl<SCK, MOSI, MISO, E> BitbangSpi<SCK, MOSI, MISO>
+where
+    SCK: OutputPin<Error = E>,
+    MOSI: OutputPin<Error = E>,
+    MISO: InputPin<Error = E>,
+{
+    /// Creates a bus on the given pins clocked at `frequency` Hz, and sets the clock to its
+    /// idle level.
+    ///
+    /// Returns [`BitbangSpiError::InvalidFrequency`] if `frequency` is zero.
+    pub fn new(
+        sck: SCK,
+        mosi: MOSI,
+        miso: MISO,
+        mode: Mode,
+        bit_order: BitOrder,
+        frequency: u32,
+    ) -> Result<Self, BitbangSpiError<E>> {
+        let mut spi = BitbangSpi {
+            sck,
+            mosi,
+            miso,
+            mode,
+            bit_order,
+            half_period: half_period(frequency)?,
+        };
+        spi.set_mode(mode)?;
+        Ok(spi)
+    }
+
+    /// Changes the SPI mode, setting the clock to its new idle level.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), BitbangSpiError<E>> {
+        self.mode = mode;
+        self.sck.set_state(self.idle_clock())?;
+        Ok(())
+    }
+
+    /// Changes the bit order.
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+
+    /// Changes the bus clock frequency, in Hz.
+    ///
+    /// Returns [`BitbangSpiError::InvalidFrequency`] if `frequency` is zero, leaving the
+    /// frequency unchanged.
+    pub fn set_frequency(&mut self, frequency: u32) -> Result<(), BitbangSpiError<E>> {
+        self.half_period = half_period(frequency)?;
+        Ok(())
+    }
+
+    /// Releases the SCK, MOSI and MISO pins.
+    pub fn release(self) -> (SCK, MOSI, MISO) {
+        (self.sck, self.mosi, self.miso)
+    }
+
+    fn idle_clock(&self) -> PinState {
+        match self.mode.polarity {
+            Polarity::IdleLow => PinState::Low,
+            Polarity::IdleHigh => PinState::High,
+        }
+    }
+
+    fn wait(&self) {
+        spin_until(Instant::now() + self.half_period);
+    }
+
+    /// Shifts `word` out on MOSI while shifting a word in from MISO.
+    fn transfer_word(&mut self, word: u8) -> Result<u8, BitbangSpiError<E>> {
+        let idle = self.idle_clock();
+        let active = !idle;
+        let mut read = 0;
+        for i in 0..8 {
+            let shift = match self.bit_order {
+                BitOrder::MsbFirst => 7 - i,
+                BitOrder::LsbFirst => i,
+            };
+            let out = PinState::from(word >> shift & 1 == 1);
+            let bit = match self.mode.phase {
+                // Data is set up while the clock is idle and sampled on the leading edge.
+                Phase::CaptureOnFirstTransition => {
+                    self.mosi.set_state(out)?;
+                    self.wait();
+                    self.sck.set_state(active)?;
+                    let bit = self.miso.is_high()?;
+                    self.wait();
+                    self.sck.set_state(idle)?;
+                    bit
+                }
+                // Data is set up on the leading edge and sampled on the trailing edge.
+                Phase::CaptureOnSecondTransition => {
+                    self.sck.set_state(active)?;
+                    self.mosi.set_state(out)?;
+                    self.wait();
+                    self.sck.set_state(idle)?;
+                    let bit = self.miso.is_high()?;
+                    self.wait();
+                    bit
+                }
+            };
+            read |= u8::from(bit) << shift;
+        }
+        Ok(read)
+    }
+}
+
+fn half_period<E>(frequency: u32) -> Result<Duration, BitbangSpiError<E>> {
+    if frequency == 0 {
+        return Err(BitbangSpiError::InvalidFrequency);
+    }
+    Ok(Duration::from_secs(1) / frequency / 2)
+}
+
+/// Error type for [`BitbangSpi`], implementing [`embedded_hal::spi::Error`]
+#[derive(Debug)]
+pub enum BitbangSpiError<E> {
+    /// An error accessing one of the pins
+    Pin(E),
+    /// The bus clock frequency was zero
+    InvalidFrequency,
+}
+
+impl<E> From<E> for BitbangSpiError<E> {
+    fn from(err: E) -> Self {
+        BitbangSpiError::Pin(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for BitbangSpiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitbangSpiError::Pin(err) => write!(f, "{}", err),
+            BitbangSpiError::InvalidFrequency => write!(f, "SPI frequency must not be zero"),
+        }
+    }
+}
+
+impl<E> std::error::Error for BitbangSpiError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BitbangSpiError::Pin(err) => Some(err),
+            BitbangSpiError::InvalidFrequency => None,
+        }
+    }
+}
+
+impl<E: fmt::Debug> embedded_hal::spi::Error for BitbangSpiError<E> {
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        use embedded_hal::spi::ErrorKind;
+        ErrorKind::Other
+    }
+}
+
+impl<SCK, MOSI, MISO, E> embedded_hal::spi::ErrorType for BitbangSpi<SCK, MOSI, MISO>
+where
+    SCK: OutputPin<Error = E>,
+    MOSI: OutputPin<Error = E>,
+    MISO: InputPin<Error = E>,
+    E: fmt::Debug,
+{
+    type Error = BitbangSpiError<E>;
+}
+
+impl<SCK, MOSI, MISO, E> embedded_hal::spi::SpiBus<u8> for BitbangSpi<SCK, MOSI, MISO>
+where
+    SCK: OutputPin<Error = E>,
+    MOSI: OutputPin<Error = E>,
+    MISO: InputPin<Error = E>,
+    E: fmt::Debug,
+{
+    /// Reads words while shifting out zeroes.
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.transfer_word(0)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        for word in words {
+            self.transfer_word(*word)?;
+        }
+        Ok(())
+    }
+
+    /// Shifts out zeroes past the end of `write` and discards words past the end of `read`.
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        for i in 0..read.len().max(write.len()) {
+            let word = self.transfer_word(write.get(i).copied().unwrap_or(0))?;
+            if let Some(r) = read.get_mut(i) {
+                *r = word;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.transfer_word(*word)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::digital::ErrorType;
+    use embedded_hal::spi::{SpiBus, MODE_0, MODE_1, MODE_2, MODE_3};
+    use std::cell::RefCell;
+    use std::convert::Infallible;
+    use std::rc::Rc;
+
+    /// Shared levels of the SCK, MOSI and MISO lines, with a device that latches MOSI on its
+    /// capture edges and answers with the complement of the previous bit it received
+    #[derive(Default)]
+    struct Bus {
+        sck: bool,
+        mosi: bool,
+        miso: bool,
+        captured: Vec<bool>,
+    }
+
+    #[derive(Clone, Copy)]
+    enum Line {
+        Sck,
+        Mosi,
+        Miso,
+    }
+
+    struct Pin {
+        bus: Rc<RefCell<Bus>>,
+        line: Line,
+        mode: Mode,
+    }
+
+    impl ErrorType for Pin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for Pin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.set_level(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set_level(true);
+            Ok(())
+        }
+    }
+
+    impl InputPin for Pin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.bus.borrow().miso)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            self.is_high().map(|val| !val)
+        }
+    }
+
+    impl Pin {
+        fn set_level(&mut self, level: bool) {
+            let mut bus = self.bus.borrow_mut();
+            match self.line {
+                Line::Sck => {
+                    if bus.sck == level {
+                        return;
+                    }
+                    bus.sck = level;
+                    let idle = self.mode.polarity == Polarity::IdleHigh;
+                    let leading = level != idle;
+                    let capture = match self.mode.phase {
+                        Phase::CaptureOnFirstTransition => leading,
+                        Phase::CaptureOnSecondTransition => !leading,
+                    };
+                    // The device shifts out on the edge opposite to the capture one.
+                    if capture {
+                        let bit = bus.mosi;
+                        bus.captured.push(bit);
+                    } else if let Some(bit) = bus.captured.last().copied() {
+                        bus.miso = !bit;
+                    }
+                }
+                Line::Mosi => bus.mosi = level,
+                Line::Miso => unreachable!(),
+            }
+        }
+    }
+
+    fn spi(mode: Mode, bit_order: BitOrder) -> (BitbangSpi<Pin, Pin, Pin>, Rc<RefCell<Bus>>) {
+        let bus = Rc::new(RefCell::new(Bus {
+            sck: mode.polarity == Polarity::IdleHigh,
+            ..Bus::default()
+        }));
+        let pin = |line| Pin {
+            bus: Rc::clone(&bus),
+            line,
+            mode,
+        };
+        let spi = BitbangSpi::new(
+            pin(Line::Sck),
+            pin(Line::Mosi),
+            pin(Line::Miso),
+            mode,
+            bit_order,
+            10_000_000,
+        )
+        .unwrap();
+        (spi, bus)
+    }
+
+    fn bits(bus: &Rc<RefCell<Bus>>) -> Vec<bool> {
+        bus.borrow().captured.clone()
+    }
+
+    #[test]
+    fn bits_are_captured_in_all_modes() {
+        for mode in [MODE_0, MODE_1, MODE_2, MODE_3] {
+            let (mut spi, bus) = spi(mode, BitOrder::MsbFirst);
+            spi.write(&[0b1010_0011]).unwrap();
+            assert_eq!(
+                bits(&bus),
+                [true, false, true, false, false, false, true, true]
+            );
+            // The clock is back to idle.
+            assert_eq!(bus.borrow().sck, mode.polarity == Polarity::IdleHigh);
+        }
+    }
+
+    #[test]
+    fn lsb_first_reverses_bits() {
+        let (mut spi, bus) = spi(MODE_0, BitOrder::LsbFirst);
+        spi.write(&[0b1010_0011]).unwrap();
+        assert_eq!(
+            bits(&bus),
+            [true, true, false, false, false, true, false, true]
+        );
+    }
+
+    #[test]
+    fn transfer_pads_and_truncates() {
+        let (mut spi, bus) = spi(MODE_0, BitOrder::MsbFirst);
+        let mut read = [0; 3];
+        spi.transfer(&mut read, &[0b1010_0011, 0b0000_1111])
+            .unwrap();
+        // Zeroes are shifted out past the end of the written words.
+        assert_eq!(bits(&bus).len(), 24);
+        assert!(bits(&bus)[16..].iter().all(|bit| !bit));
+        // The device echoes back the complement of the previous bit it received.
+        assert_eq!(read, [0b0010_1110, 0b0111_1000, 0b0111_1111]);
+
+        let mut read = [0; 1];
+        spi.transfer(&mut read, &[0b0101_1100, 0b1111_0000])
+            .unwrap();
+        assert_eq!(bits(&bus).len(), 40);
+        assert_eq!(read, [0b1101_0001]);
+    }
+
+    #[test]
+    fn zero_frequency_is_rejected() {
+        let (mut spi, _) = spi(MODE_0, BitOrder::MsbFirst);
+        assert!(matches!(
+            spi.set_frequency(0),
+            Err(BitbangSpiError::InvalidFrequency)
+        ));
+        assert_eq!(spi.half_period, Duration::from_nanos(50));
+        let (sck, mosi, miso) = spi.release();
+        assert!(matches!(
+            BitbangSpi::new(sck, mosi, miso, MODE_0, BitOrder::MsbFirst, 0),
+            Err(BitbangSpiError::InvalidFrequency)
+        ));
+    }
+}
@@ -0,0 +1,465 @@
+//! Discovery and monitoring of the GPIO chips and lines available through the Linux CDev
+//! interface
+
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use gpiocdev::chip::InfoChangeIterator;
+use gpiocdev::line::{Info, InfoChangeEvent, Offset};
+use gpiocdev::Chip;
+
+/// Wrapper around a [`gpiocdev::Chip`] describing a GPIO chip and its lines
+///
+/// Useful to check what a board provides, or that lines are wired and free as expected,
+/// before requesting them with [`CdevPin`](crate::CdevPin).
+///
+/// ```no_run
+/// use linux_embedded_hal::CdevChip;
+///
+/// for chip in CdevChip::all()? {
+///     println!("{} [{}] ({} lines)", chip.name(), chip.label(), chip.num_lines());
+///     for line in chip.lines()? {
+///         println!(
+///             "  line {:>3}: {:?} {:?} {:?} used={}",
+///             line.offset, line.name, line.consumer, line.direction, line.used
+///         );
+///     }
+/// }
+/// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+/// ```
+///
+/// [`gpiocdev::Chip`]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/chip/struct.Chip.html
+pub struct CdevChip {
+    chip: Chip,
+    info: gpiocdev::chip::Info,
+}
+
+impl CdevChip {
+    /// Opens every GPIO chip on the system, in name order.
+    pub fn all() -> Result<Vec<Self>, gpiocdev::Error> {
+        gpiocdev::chip::chips()?
+            .iter()
+            .map(CdevChip::from_path)
+            .collect()
+    }
+
+    /// Opens the GPIO chip at `path`, such as `/dev/gpiochip0`.
+    pub fn from_path<P>(path: P) -> Result<Self, gpiocdev::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let chip = Chip::from_path(path)?;
+        let info = chip.info()?;
+        Ok(CdevChip { chip, info })
+    }
+
+    /// The underlying chip
+    pub fn chip(&self) -> &Chip {
+        &self.chip
+    }
+
+    /// The path of the chip character device
+    pub fn path(&self) -> &Path {
+        self.chip.path()
+    }
+
+    /// The system name of the chip, such as `gpiochip0`
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    /// The functional name of the chip, usually identifying its driver
+    pub fn label(&self) -> &str {
+        &self.info.label
+    }
+
+    /// The number of lines provided by the chip
+    pub fn num_lines(&self) -> u32 {
+        self.info.num_lines
+    }
+
+    /// Reads the current state of the line at `offset`.
+    ///
+    /// See [`gpiocdev::line::Info`][0] for the reported attributes.
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/line/struct.Info.html
+    pub fn line_info(&self, offset: Offset) -> Result<Info, gpiocdev::Error> {
+        self.chip.line_info(offset)
+    }
+
+    /// Reads the current state of every line of the chip, in offset order.
+    pub fn lines(&self) -> Result<Vec<Info>, gpiocdev::Error> {
+        (0..self.info.num_lines)
+            .map(|offset| self.chip.line_info(offset))
+            .collect()
+    }
+}
+
+/// Watches lines of a GPIO chip for changes to their info
+///
+/// The kernel reports an event whenever a watched line is requested, released or
+/// reconfigured by any process, which allows detecting other consumers of the lines.
+///
+/// ```no_run
+/// use linux_embedded_hal::gpiocdev::line::InfoChangeKind;
+/// use linux_embedded_hal::CdevLineWatcher;
+///
+/// let mut watcher = CdevLineWatcher::new("/dev/gpiochip0")?;
+/// for offset in [17, 27] {
+///     let info = watcher.watch(offset)?;
+///     if info.used {
+///         println!("line {} already used by {:?}", offset, info.consumer);
+///     }
+/// }
+/// for event in watcher.events() {
+///     let event = event?;
+///     if event.kind == InfoChangeKind::Requested {
+///         println!("line {} requested by {:?}", event.info.offset, event.info.consumer);
+///     }
+/// }
+/// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+/// ```
+pub struct CdevLineWatcher {
+    chip: Chip,
+    lines: Vec<Offset>,
+}
+
+impl CdevLineWatcher {
+    /// Opens the GPIO chip at `path` for watching, initially with no line watched.
+    pub fn new<P>(path: P) -> Result<Self, gpiocdev::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(CdevLineWatcher {
+            chip: Chip::from_path(path)?,
+            lines: Vec::new(),
+        })
+    }
+
+    /// The underlying chip
+    pub fn chip(&self) -> &Chip {
+        &self.chip
+    }
+
+    /// The offsets of the watched lines
+    pub fn lines(&self) -> &[Offset] {
+        &self.lines
+    }
+
+    /// Starts watching the line at `offset` and returns its current info.
+    ///
+    /// Changes to the line are reported from the returned state on, so no change is missed
+    /// in between. Does nothing but return the info if the line is already watched.
+    pub fn watch(&mut self, offset: Offset) -> Result<Info, gpiocdev::Error> {
+        let info = self.chip.watch_line_info(offset)?;
+        if !self.lines.contains(&offset) {
+            self.lines.push(offset);
+        }
+        Ok(info)
+    }
+
+    /// Stops watching the line at `offset`.
+    ///
+    /// Events already queued for the line are still reported.
+    pub fn unwatch(&mut self, offset: Offset) -> Result<(), gpiocdev::Error> {
+        self.chip.unwatch_line_info(offset)?;
+        self.lines.retain(|line| *line != offset);
+        Ok(())
+    }
+
+    /// Returns true if a change event is waiting to be read.
+    pub fn has_event(&self) -> Result<bool, gpiocdev::Error> {
+        self.chip.has_line_info_change_event()
+    }
+
+    /// Reads the next change event, blocking until one is available.
+    ///
+    /// See [`gpiocdev::line::InfoChangeEvent`][0] for the reported fields.
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/line/struct.InfoChangeEvent.html
+    pub fn read_event(&self) -> Result<InfoChangeEvent, gpiocdev::Error> {
+        self.chip.read_line_info_change_event()
+    }
+
+    /// Reads the next change event, waiting up to `timeout` for one to be available.
+    ///
+    /// Returns `None` on timeout.
+    pub fn wait_event(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<InfoChangeEvent>, gpiocdev::Error> {
+        if !self.chip.wait_line_info_change_event(timeout)? {
+            return Ok(None);
+        }
+        self.read_event().map(Some)
+    }
+
+    /// Returns a blocking iterator over the change events.
+    pub fn events(&self) -> InfoChangeIterator<'_> {
+        self.chip.info_change_events()
+    }
+}
+
+/// Finds the GPIO chip and line offset of a legacy sysfs GPIO number.
+///
+/// The sysfs interface numbers the lines of all chips in a single range, starting at the
+/// `base` of each chip found in `/sys/class/gpio/gpiochipN`. This returns the path of the
+/// character device of the chip providing `gpio`, such as `/dev/gpiochip0`, and the offset of
+/// the line on that chip, to request it with [`CdevPin`](crate::CdevPin).
+///
+/// The character device is identified by the label and number of lines of the sysfs chip.
+/// The kernel must be built with sysfs GPIO support for the chip bases to be available.
+/// Returns an [`InvalidArgument`][0] error if no chip provides `gpio`, or if several
+/// character devices match the chip providing it.
+///
+/// ```no_run
+/// use linux_embedded_hal::{sysfs_gpio_to_cdev, CdevPin};
+///
+/// let (chip, offset) = sysfs_gpio_to_cdev(529)?;
+/// let pin = CdevPin::new_input(chip, offset, None)?;
+/// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+/// ```
+///
+/// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/enum.Error.html#variant.InvalidArgument
+pub fn sysfs_gpio_to_cdev(gpio: u64) -> Result<(PathBuf, Offset), gpiocdev::Error> {
+    find_sysfs_gpio(
+        Path::new("/sys/class/gpio"),
+        Path::new("/dev"),
+        gpio,
+        |path| Chip::from_path(path)?.info(),
+    )
+}
+
+/// Finds `gpio` among the chips of the sysfs GPIO interface in `sysfs_root`, returning the
+/// path of the matching chip in `dev_root`, as described by `chip_info`.
+fn find_sysfs_gpio<F>(
+    sysfs_root: &Path,
+    dev_root: &Path,
+    gpio: u64,
+    chip_info: F,
+) -> Result<(PathBuf, Offset), gpiocdev::Error>
+where
+    F: Fn(&Path) -> Result<gpiocdev::chip::Info, gpiocdev::Error>,
+{
+    for entry in fs::read_dir(sysfs_root)? {
+        let entry = entry?;
+        if !is_chip_name(&entry.file_name()) {
+            continue;
+        }
+        let path = entry.path();
+        let base = read_number(&path.join("base"))?;
+        let ngpio = read_number(&path.join("ngpio"))?;
+        if gpio < base || gpio - base >= ngpio {
+            continue;
+        }
+        let offset = Offset::try_from(gpio - base).map_err(|_| {
+            gpiocdev::Error::InvalidArgument(format!("sysfs GPIO {} out of range", gpio))
+        })?;
+        let label = fs::read_to_string(path.join("label"))?;
+        let chip = find_chip_device(dev_root, label.trim_end(), ngpio, chip_info)?;
+        return Ok((chip, offset));
+    }
+    Err(gpiocdev::Error::InvalidArgument(format!(
+        "no GPIO chip provides sysfs GPIO {}",
+        gpio
+    )))
+}
+
+/// Finds the only character device in `dev_root` of a chip with `label` and `ngpio` lines.
+///
+/// The `device` link of sysfs chips usually points to the parent device, such as a GPIO
+/// controller which may provide several chips, so it does not identify the chip. Chips that
+/// cannot be opened are skipped, and one of their errors is returned if no other matches.
+fn find_chip_device<F>(
+    dev_root: &Path,
+    label: &str,
+    ngpio: u64,
+    chip_info: F,
+) -> Result<PathBuf, gpiocdev::Error>
+where
+    F: Fn(&Path) -> Result<gpiocdev::chip::Info, gpiocdev::Error>,
+{
+    let mut found = Vec::new();
+    let mut open_error = None;
+    for entry in fs::read_dir(dev_root)? {
+        let entry = entry?;
+        if !is_chip_name(&entry.file_name()) {
+            continue;
+        }
+        match chip_info(&entry.path()) {
+            Ok(info) if info.label == label && u64::from(info.num_lines) == ngpio => {
+                found.push(entry.path());
+            }
+            Ok(_) => {}
+            Err(err) => {
+                open_error.get_or_insert(err);
+            }
+        }
+    }
+    match found.len() {
+        1 => Ok(found.remove(0)),
+        0 => Err(open_error.unwrap_or_else(|| {
+            gpiocdev::Error::InvalidArgument(format!(
+                "no GPIO chip labelled '{}' with {} lines",
+                label, ngpio
+            ))
+        })),
+        _ => {
+            found.sort();
+            Err(gpiocdev::Error::InvalidArgument(format!(
+                "several GPIO chips labelled '{}' with {} lines: {:?}",
+                label, ngpio, found
+            )))
+        }
+    }
+}
+
+/// Whether `name` is the name of a GPIO chip, such as `gpiochip0`
+fn is_chip_name(name: &OsStr) -> bool {
+    matches!(
+        name.to_str().and_then(|name| name.strip_prefix("gpiochip")),
+        Some(index) if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())
+    )
+}
+
+fn read_number(path: &Path) -> io::Result<u64> {
+    fs::read_to_string(path)?.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a number", path.display()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Temporary directory removed when dropped, even if the test fails
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "linux-embedded-hal-{}-{}",
+                name,
+                std::process::id()
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Lays out sysfs chips in `root` as `(sysfs name, base, ngpio, label)` and character
+    /// devices as `(name, label, num_lines)`, and returns the sysfs and dev directories with a
+    /// function describing the character devices. Other files in the dev directory cannot be
+    /// opened.
+    fn layout(
+        root: &Path,
+        sysfs_chips: &[(&str, u64, u64, &str)],
+        dev_chips: &'static [(&str, &str, u32)],
+    ) -> (
+        PathBuf,
+        PathBuf,
+        impl Fn(&Path) -> Result<gpiocdev::chip::Info, gpiocdev::Error>,
+    ) {
+        let (sysfs, dev) = (root.join("sysfs"), root.join("dev"));
+        for (chip, base, ngpio, label) in sysfs_chips {
+            let dir = sysfs.join(chip);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("base"), format!("{}\n", base)).unwrap();
+            fs::write(dir.join("ngpio"), format!("{}\n", ngpio)).unwrap();
+            fs::write(dir.join("label"), format!("{}\n", label)).unwrap();
+        }
+        fs::create_dir_all(&dev).unwrap();
+        for (name, _, _) in dev_chips {
+            fs::write(dev.join(name), "").unwrap();
+        }
+        let chip_info = move |path: &Path| {
+            let name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+            dev_chips
+                .iter()
+                .find(|(chip, _, _)| *chip == name)
+                .map(|(name, label, num_lines)| gpiocdev::chip::Info {
+                    name: name.to_string(),
+                    label: label.to_string(),
+                    num_lines: *num_lines,
+                })
+                .ok_or_else(|| io::Error::from(io::ErrorKind::PermissionDenied).into())
+        };
+        (sysfs, dev, chip_info)
+    }
+
+    #[test]
+    fn sysfs_gpio_maps_to_chip_offset() {
+        let root = TempDir::new("chips");
+        // Two banks of the same controller, and an expander.
+        let (sysfs, dev, chip_info) = layout(
+            &root.0,
+            &[
+                ("gpiochip512", 512, 32, "bank0"),
+                ("gpiochip544", 544, 32, "bank1"),
+                ("gpiochip576", 576, 16, "pca9555"),
+            ],
+            &[
+                ("gpiochip0", "bank0", 32),
+                ("gpiochip1", "bank1", 32),
+                ("gpiochip2", "pca9555", 16),
+            ],
+        );
+
+        let found = |gpio| find_sysfs_gpio(&sysfs, &dev, gpio, &chip_info).ok();
+        assert_eq!(found(529), Some((dev.join("gpiochip0"), 17)));
+        assert_eq!(found(544), Some((dev.join("gpiochip1"), 0)));
+        assert_eq!(found(575), Some((dev.join("gpiochip1"), 31)));
+        assert_eq!(found(591), Some((dev.join("gpiochip2"), 15)));
+        assert_eq!(found(511), None);
+        assert_eq!(found(592), None);
+    }
+
+    #[test]
+    fn inaccessible_chips_are_skipped() {
+        let root = TempDir::new("inaccessible-chips");
+        let (sysfs, dev, chip_info) = layout(
+            &root.0,
+            &[
+                ("gpiochip512", 512, 32, "bank0"),
+                ("gpiochip544", 544, 16, "pca9555"),
+            ],
+            &[("gpiochip0", "bank0", 32)],
+        );
+        fs::write(dev.join("gpiochip1"), "").unwrap();
+
+        let found = find_sysfs_gpio(&sysfs, &dev, 529, &chip_info).unwrap();
+        assert_eq!(found, (dev.join("gpiochip0"), 17));
+        // Without an accessible match, the error opening the other chip is reported.
+        let err = find_sysfs_gpio(&sysfs, &dev, 550, &chip_info).unwrap_err();
+        assert!(matches!(err, gpiocdev::Error::Os(_)));
+    }
+
+    #[test]
+    fn ambiguous_chips_are_rejected() {
+        let root = TempDir::new("ambiguous-chips");
+        let (sysfs, dev, chip_info) = layout(
+            &root.0,
+            &[
+                ("gpiochip512", 512, 32, "bank"),
+                ("gpiochip544", 544, 32, "bank"),
+            ],
+            &[("gpiochip0", "bank", 32), ("gpiochip1", "bank", 32)],
+        );
+
+        let err = find_sysfs_gpio(&sysfs, &dev, 520, &chip_info).unwrap_err();
+        assert!(matches!(err, gpiocdev::Error::InvalidArgument(_)));
+    }
+}
@@ -3,114 +3,698 @@
 //! [`embedded-hal`]: https://docs.rs/embedded-hal
 
 use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-/// Newtype around [`gpio_cdev::LineHandle`] that implements the `embedded-hal` traits
+use embedded_hal::digital::PinState;
+use gpiocdev::line::{
+    Bias, Config, Direction, Drive, EdgeDetection, EdgeKind, EventClock, Offset, Value,
+};
+use gpiocdev::Request;
+
+/// Wrapper around a single line [`gpiocdev::Request`] that implements the `embedded-hal` traits
+///
+/// Lines are requested through whichever GPIO character device uAPI version the kernel
+/// supports, preferring v2 (Linux 5.10 or later). The line configuration can be changed in
+/// place with [`CdevPin::reconfigure`] on Linux 5.5 or later, with either uAPI version, except
+/// that changing the debounce period or edge detection needs the v2 uAPI.
+///
+/// With the `async-tokio` or `async-io` feature enabled the pin also implements
+/// [`embedded_hal_async::digital::Wait`]. Waiting enables edge detection on the line, which
+/// reconfigures it as an input.
 ///
-/// [`gpio_cdev::LineHandle`]: https://docs.rs/gpio-cdev/0.5.0/gpio_cdev/struct.LineHandle.html
-pub struct CdevPin(pub gpio_cdev::LineHandle, gpio_cdev::LineInfo);
+/// [`gpiocdev::Request`]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/request/struct.Request.html
+pub struct CdevPin {
+    req: Request,
+    line: Offset,
+    config: Config,
+    /// The drive mode of the line when it was last an output
+    drive: Option<Drive>,
+}
 
 impl CdevPin {
-    /// See [`gpio_cdev::Line::request`][0] for details.
+    /// Wraps a [`gpiocdev::Request`][0] for a single line.
+    ///
+    /// Returns an [`InvalidArgument`][1] error if the request contains more than one line.
+    ///
+    /// ```no_run
+    /// use linux_embedded_hal::gpiocdev::Request;
+    /// use linux_embedded_hal::CdevPin;
+    ///
+    /// let req = Request::builder()
+    ///     .on_chip("/dev/gpiochip0")
+    ///     .with_line(17)
+    ///     .as_input()
+    ///     .request()?;
+    /// let pin = CdevPin::new(req)?;
+    /// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+    /// ```
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/request/struct.Request.html
+    /// [1]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/enum.Error.html#variant.InvalidArgument
+    pub fn new(req: Request) -> Result<Self, gpiocdev::Error> {
+        let req_config = req.config();
+        let line = match req_config.lines().as_slice() {
+            [line] => *line,
+            lines => {
+                return Err(gpiocdev::Error::InvalidArgument(format!(
+                    "CdevPin requires a request for a single line, got {} lines",
+                    lines.len()
+                )))
+            }
+        };
+        let config = req_config.line_config(line).cloned().unwrap_or_default();
+        Ok(CdevPin {
+            req,
+            line,
+            drive: config.drive,
+            config,
+        })
+    }
+
+    /// Requests `line` on the GPIO chip at `chip_path` as an input.
+    ///
+    /// `bias` enables the internal pull-up or pull-down resistor, or disables biasing
+    /// entirely. `None` leaves the bias as it is.
+    pub fn new_input<P>(
+        chip_path: P,
+        line: Offset,
+        bias: Option<Bias>,
+    ) -> Result<Self, gpiocdev::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let req = Request::builder()
+            .on_chip(chip_path.as_ref())
+            .with_line(line)
+            .as_input()
+            .with_bias(bias)
+            .request()?;
+        CdevPin::new(req)
+    }
+
+    /// Finds the line called `name` on any GPIO chip and requests it as an input.
+    ///
+    /// Line names usually come from the `gpio-line-names` device tree property. Returns an
+    /// [`InvalidArgument`][0] error if no line has that name, or a
+    /// [`NonuniqueLineName`][1] error if more than one does.
+    ///
+    /// `bias` enables the internal pull-up or pull-down resistor, or disables biasing
+    /// entirely. `None` leaves the bias as it is.
+    ///
+    /// ```no_run
+    /// use linux_embedded_hal::CdevPin;
+    ///
+    /// let button = CdevPin::new_input_by_name("USER_BUTTON", None)?;
+    /// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+    /// ```
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/enum.Error.html#variant.InvalidArgument
+    /// [1]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/enum.Error.html#variant.NonuniqueLineName
+    pub fn new_input_by_name(name: &str, bias: Option<Bias>) -> Result<Self, gpiocdev::Error> {
+        let found = find_line(name)?;
+        CdevPin::new_input(found.chip, found.info.offset, bias)
+    }
+
+    /// Finds the line called `name` on any GPIO chip and requests it as an output, initially
+    /// driven to `state`.
+    ///
+    /// Fails in the same cases as [`CdevPin::new_input_by_name`].
+    pub fn new_output_by_name(name: &str, state: PinState) -> Result<Self, gpiocdev::Error> {
+        let found = find_line(name)?;
+        let req = Request::builder()
+            .on_chip(found.chip)
+            .with_line(found.info.offset)
+            .as_output(state_to_value(state, false))
+            .request()?;
+        CdevPin::new(req)
+    }
+
+    /// Requests the line with the legacy sysfs GPIO number `gpio` as an input, with `bias`.
+    ///
+    /// The chip and offset of the line are found with
+    /// [`sysfs_gpio_to_cdev`](crate::sysfs_gpio_to_cdev), which fails if no chip provides
+    /// `gpio`. `None` leaves the bias as it is.
     ///
-    /// [0]: https://docs.rs/gpio-cdev/0.5.0/gpio_cdev/struct.Line.html#method.request
-    pub fn new(handle: gpio_cdev::LineHandle) -> Result<Self, gpio_cdev::errors::Error> {
-        let info = handle.line().info()?;
-        Ok(CdevPin(handle, info))
+    /// ```no_run
+    /// use linux_embedded_hal::CdevPin;
+    ///
+    /// let button = CdevPin::new_input_by_sysfs_number(529, None)?;
+    /// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+    /// ```
+    pub fn new_input_by_sysfs_number(
+        gpio: u64,
+        bias: Option<Bias>,
+    ) -> Result<Self, gpiocdev::Error> {
+        let (chip, offset) = crate::sysfs_gpio_to_cdev(gpio)?;
+        CdevPin::new_input(chip, offset, bias)
+    }
+
+    /// Requests the line with the legacy sysfs GPIO number `gpio` as an output, initially
+    /// driven to `state`.
+    ///
+    /// Fails in the same cases as [`CdevPin::new_input_by_sysfs_number`].
+    pub fn new_output_by_sysfs_number(gpio: u64, state: PinState) -> Result<Self, gpiocdev::Error> {
+        let (chip, offset) = crate::sysfs_gpio_to_cdev(gpio)?;
+        let req = Request::builder()
+            .on_chip(chip)
+            .with_line(offset)
+            .as_output(state_to_value(state, false))
+            .request()?;
+        CdevPin::new(req)
     }
 
-    fn get_input_flags(&self) -> gpio_cdev::LineRequestFlags {
-        if self.1.is_active_low() {
-            return gpio_cdev::LineRequestFlags::INPUT | gpio_cdev::LineRequestFlags::ACTIVE_LOW;
+    /// Starts building a pin for `line` on the GPIO chip at `chip_path`.
+    ///
+    /// ```no_run
+    /// use linux_embedded_hal::gpiocdev::line::Drive;
+    /// use linux_embedded_hal::CdevPin;
+    /// use embedded_hal::digital::PinState;
+    ///
+    /// let led = CdevPin::builder("/dev/gpiochip0", 17)
+    ///     .consumer("status-led")
+    ///     .output(PinState::Low)
+    ///     .drive(Drive::OpenDrain)
+    ///     .build()?;
+    /// # Ok::<(), linux_embedded_hal::CdevPinBuilderError>(())
+    /// ```
+    pub fn builder<P>(chip_path: P, line: Offset) -> CdevPinBuilder
+    where
+        P: AsRef<Path>,
+    {
+        CdevPinBuilder {
+            chip_path: chip_path.as_ref().to_path_buf(),
+            line,
+            consumer: None,
+            output: None,
+            active_low: false,
+            drive: None,
+            bias: None,
+            debounce_period: None,
         }
-        gpio_cdev::LineRequestFlags::INPUT
     }
 
-    fn get_output_flags(&self) -> gpio_cdev::LineRequestFlags {
-        let mut flags = gpio_cdev::LineRequestFlags::OUTPUT;
-        if self.1.is_active_low() {
-            flags.insert(gpio_cdev::LineRequestFlags::ACTIVE_LOW);
+    /// The underlying line request
+    pub fn request(&self) -> &Request {
+        &self.req
+    }
+
+    /// The offset of the line on its GPIO chip
+    pub fn line(&self) -> Offset {
+        self.line
+    }
+
+    /// The configuration currently applied to the line
+    pub fn line_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Changes the configuration of the line without releasing it.
+    ///
+    /// Needs Linux 5.5 or later. Changing the debounce period or edge detection of the line,
+    /// or any setting of a line with edge detection, needs the v2 uAPI. Changing the bias,
+    /// debounce period, direction or edge detection of the pin goes through this method as well.
+    ///
+    /// See [`gpiocdev::Request::reconfigure`][0] for details.
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/request/struct.Request.html#method.reconfigure
+    pub fn reconfigure(&mut self, config: Config) -> Result<(), gpiocdev::Error> {
+        let mut req_config = self.req.config();
+        req_config.with_line(self.line).from_line_config(&config);
+        self.req.reconfigure(&req_config)?;
+        if config.direction == Some(Direction::Output) {
+            self.drive = config.drive;
         }
-        if self.1.is_open_drain() {
-            flags.insert(gpio_cdev::LineRequestFlags::OPEN_DRAIN);
-        } else if self.1.is_open_source() {
-            flags.insert(gpio_cdev::LineRequestFlags::OPEN_SOURCE);
+        self.config = config;
+        Ok(())
+    }
+
+    /// Changes the bias of the line, keeping its direction.
+    ///
+    /// `None` leaves the bias as it is. See [`CdevPin::reconfigure`] for kernel requirements.
+    pub fn set_bias(&mut self, bias: Option<Bias>) -> Result<(), gpiocdev::Error> {
+        if self.config.bias == bias {
+            return Ok(());
         }
-        flags
+
+        let mut config = self.config.clone();
+        config.bias = bias;
+        self.reconfigure(config)
+    }
+
+    /// The debounce period applied to the line by the kernel, if any
+    pub fn debounce_period(&self) -> Option<Duration> {
+        self.config.debounce_period
+    }
+
+    /// Changes the debounce period of an input line.
+    ///
+    /// Once set, the kernel filters out edges shorter than `period`, for both the values read
+    /// through [`InputPin`](embedded_hal::digital::InputPin) and the reported edge events. A
+    /// zero `period` disables debouncing. Debouncing needs the v2 uAPI.
+    ///
+    /// To debounce the line from the moment it is requested, pass a request built with
+    /// [`gpiocdev::request::Builder::with_debounce_period`][0] to [`CdevPin::new`] instead.
+    ///
+    /// Returns an [`InvalidArgument`][1] error if the pin is an output, as debouncing only
+    /// applies to inputs. Switching the pin to an output clears the debounce period.
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/request/struct.Builder.html#method.with_debounce_period
+    /// [1]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/enum.Error.html#variant.InvalidArgument
+    pub fn set_debounce_period(&mut self, period: Duration) -> Result<(), gpiocdev::Error> {
+        if self.config.direction == Some(Direction::Output) {
+            return Err(gpiocdev::Error::InvalidArgument(
+                "debounce period can only be set on input lines".to_string(),
+            ));
+        }
+
+        let mut config = self.config.clone();
+        config.with_debounce_period(period);
+        if config.debounce_period == self.config.debounce_period {
+            return Ok(());
+        }
+        self.reconfigure(config)
+    }
+
+    /// The current direction of the line, if known
+    pub fn direction(&self) -> Option<Direction> {
+        self.config.direction
+    }
+
+    /// Switches the line to an input in place.
+    ///
+    /// The line is reconfigured without being released, so no other process can claim it in
+    /// between. Together with [`CdevPin::set_as_output`] this allows using the pin for
+    /// bidirectional protocols such as the single wire bus of DHT sensors. The bias of the
+    /// line is preserved. See [`CdevPin::reconfigure`] for kernel requirements.
+    pub fn set_as_input(&mut self) -> Result<(), gpiocdev::Error> {
+        if self.config.direction == Some(Direction::Input) {
+            return Ok(());
+        }
+
+        let mut config = self.config.clone();
+        config.as_input();
+        self.reconfigure(config)
+    }
+
+    /// Switches the line to an output driven to `state` in place.
+    ///
+    /// The line is reconfigured without being released and is driven to `state` as soon as
+    /// it becomes an output, so it never glitches through another level. Does nothing if the
+    /// line is already an output. The bias of the line is preserved, and so is the drive mode
+    /// it had when last an output, such as open-drain. See [`CdevPin::reconfigure`] for kernel
+    /// requirements.
+    pub fn set_as_output(&mut self, state: PinState) -> Result<(), gpiocdev::Error> {
+        if self.config.direction == Some(Direction::Output) {
+            return Ok(());
+        }
+
+        let config = output_config(&self.config, self.drive, state);
+        self.reconfigure(config)
     }
 
     /// Set this pin to input mode
-    pub fn into_input_pin(self) -> Result<CdevPin, gpio_cdev::errors::Error> {
-        if self.1.direction() == gpio_cdev::LineDirection::In {
+    ///
+    /// The bias of the line is preserved. See [`CdevPin::set_as_input`].
+    pub fn into_input_pin(mut self) -> Result<CdevPin, gpiocdev::Error> {
+        self.set_as_input()?;
+        Ok(self)
+    }
+
+    /// Set this pin to input mode with the given bias
+    ///
+    /// `None` leaves the bias as it is.
+    pub fn into_input_pin_with_bias(
+        mut self,
+        bias: Option<Bias>,
+    ) -> Result<CdevPin, gpiocdev::Error> {
+        if self.config.direction == Some(Direction::Input) && self.config.bias == bias {
             return Ok(self);
         }
-        let line = self.0.line().clone();
-        let input_flags = self.get_input_flags();
-        let consumer = self.1.consumer().unwrap_or("").to_owned();
-
-        // Drop self to free the line before re-requesting it in a new mode.
-        std::mem::drop(self);
 
-        CdevPin::new(line.request(input_flags, 0, &consumer)?)
+        let mut config = self.config.clone();
+        config.as_input();
+        config.bias = bias;
+        self.reconfigure(config)?;
+        Ok(self)
     }
 
     /// Set this pin to output mode
-    pub fn into_output_pin(
-        self,
-        state: embedded_hal::digital::PinState,
-    ) -> Result<CdevPin, gpio_cdev::errors::Error> {
-        if self.1.direction() == gpio_cdev::LineDirection::Out {
-            return Ok(self);
+    ///
+    /// The bias and drive mode of the line are preserved. See [`CdevPin::set_as_output`].
+    pub fn into_output_pin(mut self, state: PinState) -> Result<CdevPin, gpiocdev::Error> {
+        self.set_as_output(state)?;
+        Ok(self)
+    }
+
+    /// Enables detection of `edges` and returns a blocking iterator over the detected edges.
+    ///
+    /// Edges refer to the physical line level, like [`InputPin::is_high`], so a rising edge
+    /// is a transition from low to high even on an active-low line. Event timestamps are
+    /// taken from `clock`.
+    ///
+    /// The line is reconfigured as an input with edge detection if necessary, which needs the
+    /// v2 uAPI. Edge detection stays enabled after the iterator is dropped, and events keep
+    /// being queued by the kernel until they are read with [`CdevPin::read_edge_event`] or a
+    /// new iterator.
+    ///
+    /// ```no_run
+    /// use linux_embedded_hal::gpiocdev::line::{EdgeDetection, EventClock};
+    /// use linux_embedded_hal::CdevPin;
+    ///
+    /// let mut pin = CdevPin::new_input("/dev/gpiochip0", 17, None)?;
+    /// let mut last = None;
+    /// for event in pin.edge_events(EdgeDetection::RisingEdge, EventClock::Monotonic)? {
+    ///     let event = event?;
+    ///     if let Some(last) = last.replace(event.timestamp_ns) {
+    ///         println!("period: {} ns", event.timestamp_ns - last);
+    ///     }
+    /// }
+    /// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+    /// ```
+    ///
+    /// [`InputPin::is_high`]: embedded_hal::digital::InputPin::is_high
+    pub fn edge_events(
+        &mut self,
+        edges: EdgeDetection,
+        clock: EventClock,
+    ) -> Result<CdevEdgeEvents<'_>, gpiocdev::Error> {
+        self.enable_edge_detection(edges, clock)?;
+        Ok(CdevEdgeEvents { pin: self })
+    }
+
+    /// Blocks until the line undergoes a transition matching `edges` or `timeout` elapses.
+    ///
+    /// Returns true if an edge was detected and false on timeout. Edges refer to the physical
+    /// line level, as for [`CdevPin::edge_events`], and only transitions occurring after the
+    /// call are taken into account.
+    ///
+    /// The line is reconfigured as an input with edge detection if necessary, and stays so
+    /// after the call returns.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use linux_embedded_hal::gpiocdev::line::EdgeDetection;
+    /// use linux_embedded_hal::CdevPin;
+    ///
+    /// let mut drdy = CdevPin::new_input("/dev/gpiochip0", 17, None)?;
+    /// if !drdy.wait_for_edge(EdgeDetection::FallingEdge, Duration::from_millis(200))? {
+    ///     println!("timed out waiting for data");
+    /// }
+    /// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+    /// ```
+    pub fn wait_for_edge(
+        &mut self,
+        edges: EdgeDetection,
+        timeout: Duration,
+    ) -> Result<bool, gpiocdev::Error> {
+        let clock = self.config.event_clock.unwrap_or_default();
+        self.enable_edge_detection(edges, clock)?;
+        while self.req.has_edge_event()? {
+            self.req.read_edge_event()?;
         }
 
-        let line = self.0.line().clone();
-        let output_flags = self.get_output_flags();
-        let consumer = self.1.consumer().unwrap_or("").to_owned();
+        if !self.req.wait_edge_event(timeout)? {
+            return Ok(false);
+        }
+        self.req.read_edge_event()?;
+        Ok(true)
+    }
 
-        // Drop self to free the line before re-requesting it in a new mode.
-        std::mem::drop(self);
+    /// Enables detection of the physical `edges`, timestamped with `clock`.
+    fn enable_edge_detection(
+        &mut self,
+        edges: EdgeDetection,
+        clock: EventClock,
+    ) -> Result<(), gpiocdev::Error> {
+        let edges = swap_edge_detection(edges, self.config.active_low);
+        if self.config.edge_detection == Some(edges) && self.config.event_clock == Some(clock) {
+            return Ok(());
+        }
 
-        CdevPin::new(line.request(
-            output_flags,
-            state_to_value(
-                state,
-                output_flags.intersects(gpio_cdev::LineRequestFlags::ACTIVE_LOW),
-            ),
-            &consumer,
-        )?)
+        let mut config = self.config.clone();
+        config.with_edge_detection(edges).event_clock = Some(clock);
+        self.reconfigure(config)
+    }
+
+    /// Returns true if an edge event is waiting to be read.
+    pub fn has_edge_event(&self) -> Result<bool, gpiocdev::Error> {
+        self.req.has_edge_event()
+    }
+
+    /// Reads the next edge event, blocking until one is available.
+    ///
+    /// Edge detection must have been enabled with [`CdevPin::edge_events`].
+    pub fn read_edge_event(&self) -> Result<CdevEdgeEvent, gpiocdev::Error> {
+        let event = self.req.read_edge_event()?;
+        Ok(CdevEdgeEvent {
+            kind: swap_edge_kind(event.kind, self.config.active_low),
+            timestamp_ns: event.timestamp_ns,
+            seqno: event.seqno,
+            line_seqno: event.line_seqno,
+        })
     }
 }
 
-/// Converts a pin state to the gpio_cdev compatible numeric value, accounting
+/// Builder for a [`CdevPin`], returned by [`CdevPin::builder`]
+///
+/// The line is requested as an input unless [`CdevPinBuilder::output`] is called.
+#[derive(Clone, Debug)]
+pub struct CdevPinBuilder {
+    chip_path: PathBuf,
+    line: Offset,
+    consumer: Option<String>,
+    output: Option<PinState>,
+    active_low: bool,
+    drive: Option<Drive>,
+    bias: Option<Bias>,
+    debounce_period: Option<Duration>,
+}
+
+impl CdevPinBuilder {
+    /// Sets the consumer label reported by the kernel for the line.
+    pub fn consumer<N: Into<String>>(mut self, consumer: N) -> Self {
+        self.consumer = Some(consumer.into());
+        self
+    }
+
+    /// Requests the line as an input.
+    pub fn input(mut self) -> Self {
+        self.output = None;
+        self
+    }
+
+    /// Requests the line as an output, initially driven to `state`.
+    pub fn output(mut self, state: PinState) -> Self {
+        self.output = Some(state);
+        self
+    }
+
+    /// Marks the line as active-low.
+    ///
+    /// Pin states remain physical levels, as for [`CdevPin::new`].
+    pub fn active_low(mut self, active_low: bool) -> Self {
+        self.active_low = active_low;
+        self
+    }
+
+    /// Sets the drive mode of an output line.
+    pub fn drive(mut self, drive: Drive) -> Self {
+        self.drive = Some(drive);
+        self
+    }
+
+    /// Sets the bias of the line.
+    pub fn bias(mut self, bias: Bias) -> Self {
+        self.bias = Some(bias);
+        self
+    }
+
+    /// Sets the debounce period of an input line.
+    pub fn debounce_period(mut self, period: Duration) -> Self {
+        self.debounce_period = Some(period);
+        self
+    }
+
+    /// Requests the line and wraps it in a [`CdevPin`].
+    ///
+    /// Fails if a drive mode is set on an input or a debounce period on an output, and with
+    /// [`CdevPinBuilderError::Request`] if the kernel refuses the line request.
+    pub fn build(self) -> Result<CdevPin, CdevPinBuilderError> {
+        let mut builder = Request::builder();
+        builder.on_chip(self.chip_path).with_line(self.line);
+        if let Some(consumer) = self.consumer {
+            builder.with_consumer(consumer);
+        }
+        if self.active_low {
+            builder.as_active_low();
+        }
+        match self.output {
+            Some(state) => {
+                if self.debounce_period.is_some() {
+                    return Err(CdevPinBuilderError::DebounceOnOutput);
+                }
+                builder.as_output(state_to_value(state, self.active_low));
+                if let Some(drive) = self.drive {
+                    builder.with_drive(drive);
+                }
+            }
+            None => {
+                if self.drive.is_some() {
+                    return Err(CdevPinBuilderError::DriveOnInput);
+                }
+                builder.as_input();
+                if let Some(period) = self.debounce_period {
+                    builder.with_debounce_period(period);
+                }
+            }
+        }
+        builder.with_bias(self.bias);
+        Ok(CdevPin::new(builder.request()?)?)
+    }
+}
+
+/// Error returned by [`CdevPinBuilder::build`]
+#[derive(Debug)]
+pub enum CdevPinBuilderError {
+    /// A drive mode was set on an input line
+    DriveOnInput,
+    /// A debounce period was set on an output line
+    DebounceOnOutput,
+    /// The line could not be requested
+    Request(gpiocdev::Error),
+}
+
+impl From<gpiocdev::Error> for CdevPinBuilderError {
+    fn from(err: gpiocdev::Error) -> Self {
+        CdevPinBuilderError::Request(err)
+    }
+}
+
+impl fmt::Display for CdevPinBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CdevPinBuilderError::DriveOnInput => {
+                write!(f, "drive mode can only be set on output lines")
+            }
+            CdevPinBuilderError::DebounceOnOutput => {
+                write!(f, "debounce period can only be set on input lines")
+            }
+            CdevPinBuilderError::Request(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CdevPinBuilderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CdevPinBuilderError::Request(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// An edge detected on a [`CdevPin`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CdevEdgeEvent {
+    /// Whether the physical line level rose or fell
+    pub kind: EdgeKind,
+
+    /// Best estimate of the time the edge occurred, in nanoseconds
+    ///
+    /// The timestamp is taken from the [`EventClock`] selected in [`CdevPin::edge_events`],
+    /// so only timestamps taken from the same clock can be compared.
+    pub timestamp_ns: u64,
+
+    /// Sequence number of this event among all events of the line request
+    pub seqno: u32,
+
+    /// Sequence number of this event among the events of this line
+    ///
+    /// Gaps in the sequence indicate that the kernel event buffer overflowed.
+    pub line_seqno: u32,
+}
+
+/// Blocking iterator over the edges detected on a [`CdevPin`]
+///
+/// Returned by [`CdevPin::edge_events`].
+pub struct CdevEdgeEvents<'a> {
+    pin: &'a CdevPin,
+}
+
+impl Iterator for CdevEdgeEvents<'_> {
+    type Item = Result<CdevEdgeEvent, gpiocdev::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.pin.read_edge_event())
+    }
+}
+
+/// Finds the only line called `name` across all GPIO chips.
+fn find_line(name: &str) -> Result<gpiocdev::FoundLine, gpiocdev::Error> {
+    gpiocdev::find_named_lines(&[name], true)?
+        .remove(name)
+        .ok_or_else(|| gpiocdev::Error::InvalidArgument(format!("no GPIO line named '{}'", name)))
+}
+
+/// The configuration switching the line configured with `config` to an output driven to
+/// `state`, with `drive`.
+fn output_config(config: &Config, drive: Option<Drive>, state: PinState) -> Config {
+    let mut config = config.clone();
+    config.as_output(state_to_value(state, config.active_low));
+    config.drive = drive;
+    config
+}
+
+/// Converts between physical and logical edge detection settings for active-low lines.
+fn swap_edge_detection(edges: EdgeDetection, is_active_low: bool) -> EdgeDetection {
+    match (edges, is_active_low) {
+        (EdgeDetection::RisingEdge, true) => EdgeDetection::FallingEdge,
+        (EdgeDetection::FallingEdge, true) => EdgeDetection::RisingEdge,
+        (edges, _) => edges,
+    }
+}
+
+/// Converts between physical and logical edges for active-low lines.
+fn swap_edge_kind(kind: EdgeKind, is_active_low: bool) -> EdgeKind {
+    match (kind, is_active_low) {
+        (EdgeKind::Rising, true) => EdgeKind::Falling,
+        (EdgeKind::Falling, true) => EdgeKind::Rising,
+        (kind, _) => kind,
+    }
+}
+
+/// Converts a pin state to the gpiocdev compatible logical value, accounting
 /// for the active_low condition.
-fn state_to_value(state: embedded_hal::digital::PinState, is_active_low: bool) -> u8 {
+pub(crate) fn state_to_value(state: PinState, is_active_low: bool) -> Value {
     if is_active_low {
         match state {
-            embedded_hal::digital::PinState::High => 0,
-            embedded_hal::digital::PinState::Low => 1,
+            PinState::High => Value::Inactive,
+            PinState::Low => Value::Active,
         }
     } else {
         match state {
-            embedded_hal::digital::PinState::High => 1,
-            embedded_hal::digital::PinState::Low => 0,
+            PinState::High => Value::Active,
+            PinState::Low => Value::Inactive,
         }
     }
 }
 
-/// Error type wrapping [gpio_cdev::errors::Error](gpio_cdev::errors::Error) to implement [embedded_hal::digital::Error]
-#[derive(Debug)]
+/// Error type wrapping [gpiocdev::Error](gpiocdev::Error) to implement [embedded_hal::digital::Error]
+#[derive(Clone, Debug)]
 pub struct CdevPinError {
-    err: gpio_cdev::errors::Error,
+    err: gpiocdev::Error,
 }
 
 impl CdevPinError {
-    /// Fetch inner (concrete) [`gpio_cdev::errors::Error`]
-    pub fn inner(&self) -> &gpio_cdev::errors::Error {
+    /// Fetch inner (concrete) [`gpiocdev::Error`]
+    pub fn inner(&self) -> &gpiocdev::Error {
         &self.err
     }
 }
 
-impl From<gpio_cdev::errors::Error> for CdevPinError {
-    fn from(err: gpio_cdev::errors::Error) -> Self {
+impl From<gpiocdev::Error> for CdevPinError {
+    fn from(err: gpiocdev::Error) -> Self {
         Self { err }
     }
 }
@@ -140,52 +724,140 @@ impl embedded_hal::digital::ErrorType for CdevPin {
 
 impl embedded_hal::digital::OutputPin for CdevPin {
     fn set_low(&mut self) -> Result<(), Self::Error> {
-        self.0
-            .set_value(state_to_value(
-                embedded_hal::digital::PinState::Low,
-                self.1.is_active_low(),
-            ))
+        self.req
+            .set_value(
+                self.line,
+                state_to_value(PinState::Low, self.config.active_low),
+            )
             .map_err(CdevPinError::from)
     }
 
     fn set_high(&mut self) -> Result<(), Self::Error> {
-        self.0
-            .set_value(state_to_value(
-                embedded_hal::digital::PinState::High,
-                self.1.is_active_low(),
-            ))
+        self.req
+            .set_value(
+                self.line,
+                state_to_value(PinState::High, self.config.active_low),
+            )
             .map_err(CdevPinError::from)
     }
 }
 
+impl embedded_hal::digital::StatefulOutputPin for CdevPin {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        self.req
+            .value(self.line)
+            .map(|val| val == state_to_value(PinState::High, self.config.active_low))
+            .map_err(CdevPinError::from)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_set_high().map(|val| !val)
+    }
+}
+
 impl embedded_hal::digital::InputPin for CdevPin {
-    fn is_high(&self) -> Result<bool, Self::Error> {
-        self.0
-            .get_value()
-            .map(|val| {
-                val == state_to_value(
-                    embedded_hal::digital::PinState::High,
-                    self.1.is_active_low(),
-                )
-            })
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.req
+            .value(self.line)
+            .map(|val| val == state_to_value(PinState::High, self.config.active_low))
             .map_err(CdevPinError::from)
     }
 
-    fn is_low(&self) -> Result<bool, Self::Error> {
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
         self.is_high().map(|val| !val)
     }
 }
 
-impl core::ops::Deref for CdevPin {
-    type Target = gpio_cdev::LineHandle;
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+impl CdevPin {
+    /// Waits until the pin is at `level` or undergoes a transition matching `edge`.
+    ///
+    /// `level` and `edge` refer to the physical line state, in line with
+    /// [`InputPin::is_high`](embedded_hal::digital::InputPin::is_high).
+    async fn wait_for(
+        &mut self,
+        level: Option<PinState>,
+        edge: Option<EdgeKind>,
+    ) -> Result<(), CdevPinError> {
+        use crate::async_fd::AsyncFd;
+        use std::os::unix::io::AsFd;
+
+        if self.config.edge_detection != Some(EdgeDetection::BothEdges) {
+            let mut config = self.config.clone();
+            config.with_edge_detection(EdgeDetection::BothEdges);
+            self.reconfigure(config)?;
+        }
+
+        // Discard events left queued by an earlier wait.
+        while self.has_edge_event()? {
+            self.read_edge_event()?;
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+        if let Some(state) = level {
+            if self.req.value(self.line)? == state_to_value(state, self.config.active_low) {
+                return Ok(());
+            }
+        }
+
+        let expected = match level {
+            Some(PinState::High) => Some(EdgeKind::Rising),
+            Some(PinState::Low) => Some(EdgeKind::Falling),
+            None => edge,
+        };
+        let fd = AsyncFd::new(self.req.as_fd()).map_err(gpiocdev::Error::from)?;
+        loop {
+            while self.has_edge_event()? {
+                let event = self.read_edge_event()?;
+                if expected.is_none() || expected == Some(event.kind) {
+                    return Ok(());
+                }
+            }
+            fd.readable().await.map_err(gpiocdev::Error::from)?;
+        }
     }
 }
 
-impl core::ops::DerefMut for CdevPin {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+impl embedded_hal_async::digital::Wait for CdevPin {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.wait_for(Some(PinState::High), None).await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.wait_for(Some(PinState::Low), None).await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for(None, Some(EdgeKind::Rising)).await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for(None, Some(EdgeKind::Falling)).await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for(None, None).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_round_trip_keeps_drive() {
+        let mut output = Config::default();
+        output.as_output(Value::Inactive).drive = Some(Drive::OpenDrain);
+        output.bias = Some(Bias::PullUp);
+
+        let mut input = output.clone();
+        input.as_input();
+        assert_eq!(input.drive, None);
+
+        let config = output_config(&input, output.drive, PinState::High);
+        assert_eq!(config.direction, Some(Direction::Output));
+        assert_eq!(config.value, Some(Value::Active));
+        assert_eq!(config.drive, Some(Drive::OpenDrain));
+        assert_eq!(config.bias, Some(Bias::PullUp));
     }
 }
@@ -0,0 +1,302 @@
+//! Implementation of [`embedded-hal`] digital input/output traits for groups of Linux CDev pins
+//!
+//! [`embedded-hal`]: https://docs.rs/embedded-hal
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use embedded_hal::digital::PinState;
+use gpiocdev::line::{Bias, Offset, Value, Values};
+use gpiocdev::Request;
+
+use crate::cdev_pin::state_to_value;
+use crate::CdevPinError;
+
+/// The maximum number of lines in a single request, as defined by the kernel uAPI
+const MAX_LINES: usize = 64;
+
+/// A set of lines on one GPIO chip, requested together so their values can be read and
+/// written atomically
+///
+/// Values are exchanged as bitmasks where bit `n` holds the physical level of the `n`th line
+/// of the group, `1` being high. Individual lines can be borrowed as pins implementing the
+/// `embedded-hal` traits with [`CdevPinGroup::pin`].
+///
+/// ```no_run
+/// use linux_embedded_hal::CdevPinGroup;
+///
+/// // An 8-bit parallel bus, D0 on line 20 up to D7 on line 27.
+/// let bus = CdevPinGroup::new_output("/dev/gpiochip0", &[20, 21, 22, 23, 24, 25, 26, 27], 0)?;
+/// bus.set_values(0xA5)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct CdevPinGroup {
+    req: Request,
+    lines: Vec<Offset>,
+    active_low: u64,
+}
+
+impl CdevPinGroup {
+    /// Wraps a multi-line [`gpiocdev::Request`][0].
+    ///
+    /// The lines of the group are ordered as they were added to the request.
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/request/struct.Request.html
+    pub fn new(req: Request) -> Result<Self, gpiocdev::Error> {
+        let req_config = req.config();
+        let lines = req_config.lines().clone();
+        let active_low = lines.iter().enumerate().fold(0, |mask, (i, line)| {
+            let is_active_low =
+                matches!(req_config.line_config(*line), Some(config) if config.active_low);
+            mask | (u64::from(is_active_low) << i)
+        });
+        Ok(CdevPinGroup {
+            req,
+            lines,
+            active_low,
+        })
+    }
+
+    /// Requests `lines` on the GPIO chip at `chip_path` as inputs.
+    ///
+    /// The lines of the group are ordered as in `lines`. `bias` is applied to every line, with
+    /// `None` leaving it as it is.
+    pub fn new_input<P>(
+        chip_path: P,
+        lines: &[Offset],
+        bias: Option<Bias>,
+    ) -> Result<Self, gpiocdev::Error>
+    where
+        P: AsRef<Path>,
+    {
+        check_lines(lines)?;
+        let req = Request::builder()
+            .on_chip(chip_path.as_ref())
+            .with_lines(lines)
+            .as_input()
+            .with_bias(bias)
+            .request()?;
+        Ok(CdevPinGroup {
+            req,
+            lines: lines.to_vec(),
+            active_low: 0,
+        })
+    }
+
+    /// Requests `lines` on the GPIO chip at `chip_path` as outputs, initially driven to the
+    /// levels in the `values` bitmask.
+    ///
+    /// The lines of the group are ordered as in `lines`.
+    pub fn new_output<P>(
+        chip_path: P,
+        lines: &[Offset],
+        values: u64,
+    ) -> Result<Self, gpiocdev::Error>
+    where
+        P: AsRef<Path>,
+    {
+        check_lines(lines)?;
+        let mut builder = Request::builder();
+        builder.on_chip(chip_path.as_ref());
+        for (i, line) in lines.iter().enumerate() {
+            builder
+                .with_line(*line)
+                .as_output(Value::from(values >> i & 1 == 1));
+        }
+        Ok(CdevPinGroup {
+            req: builder.request()?,
+            lines: lines.to_vec(),
+            active_low: 0,
+        })
+    }
+
+    /// The underlying line request
+    pub fn request(&self) -> &Request {
+        &self.req
+    }
+
+    /// The offsets of the lines in the group, in bitmask order
+    pub fn lines(&self) -> &[Offset] {
+        &self.lines
+    }
+
+    /// Reads the levels of all lines at once.
+    pub fn get_values(&self) -> Result<u64, CdevPinError> {
+        let mut values = Values::from_offsets(&self.lines);
+        self.req.values(&mut values)?;
+        Ok(values_to_levels(&self.lines, self.active_low, &values))
+    }
+
+    /// Drives all lines at once to the levels in the `values` bitmask.
+    pub fn set_values(&self, values: u64) -> Result<(), CdevPinError> {
+        self.set_values_masked(u64::MAX, values)
+    }
+
+    /// Drives the lines selected by `mask` at once to the levels in the `values` bitmask,
+    /// leaving the other lines untouched.
+    pub fn set_values_masked(&self, mask: u64, values: u64) -> Result<(), CdevPinError> {
+        let values = levels_to_values(&self.lines, self.active_low, mask, values);
+        if values.is_empty() {
+            return Ok(());
+        }
+        self.req.set_values(&values).map_err(CdevPinError::from)
+    }
+
+    /// Borrows the `index`th line of the group as a single pin.
+    ///
+    /// Returns `None` if `index` is out of range.
+    pub fn pin(&self, index: usize) -> Option<CdevGroupPin<'_>> {
+        if index < self.lines.len() {
+            Some(CdevGroupPin { group: self, index })
+        } else {
+            None
+        }
+    }
+
+    /// Borrows every line of the group as a single pin, in bitmask order.
+    pub fn pins(&self) -> Vec<CdevGroupPin<'_>> {
+        (0..self.lines.len())
+            .map(|index| CdevGroupPin { group: self, index })
+            .collect()
+    }
+
+    fn is_active_low(&self, index: usize) -> bool {
+        self.active_low >> index & 1 == 1
+    }
+}
+
+/// Converts the logical line `values` into a bitmask of physical levels, bit `n` holding the
+/// level of `lines[n]`.
+fn values_to_levels(lines: &[Offset], active_low: u64, values: &Values) -> u64 {
+    lines.iter().enumerate().fold(0, |levels, (i, line)| {
+        let is_high =
+            matches!(values.get(*line), Some(Value::Active)) != (active_low >> i & 1 == 1);
+        levels | (u64::from(is_high) << i)
+    })
+}
+
+/// Converts the physical `levels` of the lines selected by `mask` into logical line values.
+fn levels_to_values(lines: &[Offset], active_low: u64, mask: u64, levels: u64) -> Values {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| mask >> i & 1 == 1)
+        .map(|(i, line)| {
+            let state = PinState::from(levels >> i & 1 == 1);
+            (*line, state_to_value(state, active_low >> i & 1 == 1))
+        })
+        .collect()
+}
+
+/// Checks that `lines` fits in a single request and in the bitmasks.
+fn check_lines(lines: &[Offset]) -> Result<(), gpiocdev::Error> {
+    if lines.is_empty() || lines.len() > MAX_LINES {
+        return Err(gpiocdev::Error::InvalidArgument(format!(
+            "CdevPinGroup requires between 1 and {} lines, got {}",
+            MAX_LINES,
+            lines.len()
+        )));
+    }
+    if lines.iter().collect::<HashSet<_>>().len() != lines.len() {
+        return Err(gpiocdev::Error::InvalidArgument(
+            "CdevPinGroup lines must be unique".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// A single line borrowed from a [`CdevPinGroup`]
+///
+/// Returned by [`CdevPinGroup::pin`] and [`CdevPinGroup::pins`].
+pub struct CdevGroupPin<'a> {
+    group: &'a CdevPinGroup,
+    index: usize,
+}
+
+impl CdevGroupPin<'_> {
+    /// The offset of the line on its GPIO chip
+    pub fn line(&self) -> Offset {
+        self.group.lines[self.index]
+    }
+
+    fn state_to_value(&self, state: PinState) -> Value {
+        state_to_value(state, self.group.is_active_low(self.index))
+    }
+}
+
+impl embedded_hal::digital::ErrorType for CdevGroupPin<'_> {
+    type Error = CdevPinError;
+}
+
+impl embedded_hal::digital::OutputPin for CdevGroupPin<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.group
+            .req
+            .set_value(self.line(), self.state_to_value(PinState::Low))
+            .map_err(CdevPinError::from)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.group
+            .req
+            .set_value(self.line(), self.state_to_value(PinState::High))
+            .map_err(CdevPinError::from)
+    }
+}
+
+impl embedded_hal::digital::InputPin for CdevGroupPin<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.group
+            .req
+            .value(self.line())
+            .map(|val| val == self.state_to_value(PinState::High))
+            .map_err(CdevPinError::from)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|val| !val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_map_to_lines_in_group_order() {
+        let lines = [7, 3, 5];
+        let values = levels_to_values(&lines, 0, u64::MAX, 0b011);
+        assert_eq!(values.get(7), Some(Value::Active));
+        assert_eq!(values.get(3), Some(Value::Active));
+        assert_eq!(values.get(5), Some(Value::Inactive));
+        assert_eq!(values_to_levels(&lines, 0, &values), 0b011);
+    }
+
+    #[test]
+    fn mask_selects_lines_to_set() {
+        let lines = [7, 3, 5];
+        let values = levels_to_values(&lines, 0, 0b101, 0b111);
+        assert_eq!(values.len(), 2);
+        assert_eq!(values.get(7), Some(Value::Active));
+        assert_eq!(values.get(3), None);
+        assert_eq!(values.get(5), Some(Value::Active));
+        assert!(levels_to_values(&lines, 0, 0, 0b111).is_empty());
+    }
+
+    #[test]
+    fn active_low_lines_are_inverted() {
+        let lines = [7, 3, 5];
+        let active_low = 0b010;
+        let values = levels_to_values(&lines, active_low, u64::MAX, 0b010);
+        assert_eq!(values.get(7), Some(Value::Inactive));
+        assert_eq!(values.get(3), Some(Value::Inactive));
+        assert_eq!(values.get(5), Some(Value::Inactive));
+        assert_eq!(values_to_levels(&lines, active_low, &values), 0b010);
+
+        let values: Values = [(7, Value::Active), (3, Value::Active), (5, Value::Inactive)]
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(values_to_levels(&lines, active_low, &values), 0b001);
+    }
+}
@@ -2,19 +2,52 @@
 //!
 //! [`embedded-hal`]: https://docs.rs/embedded-hal
 
-use cast::u64;
-use embedded_hal::delay::DelayUs;
+use embedded_hal::delay::DelayNs;
+use std::hint;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Empty struct that provides delay functionality on top of `thread::sleep`
 pub struct Delay;
 
-impl DelayUs for Delay {
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, n: u32) {
+        thread::sleep(Duration::from_nanos(n.into()));
+    }
+
     fn delay_us(&mut self, n: u32) {
-        let secs = n / 1_000_000;
-        let nsecs = (n % 1_000_000) * 1_000;
+        thread::sleep(Duration::from_micros(n.into()));
+    }
+
+    fn delay_ms(&mut self, n: u32) {
+        thread::sleep(Duration::from_millis(n.into()));
+    }
+}
+
+/// Empty struct that provides delay functionality by busy-waiting
+///
+/// `thread::sleep` usually overshoots by tens of microseconds, which breaks bit-banged
+/// protocols relying on microsecond timing, such as [`OneWireBus`](crate::OneWireBus). This
+/// delay keeps the CPU busy instead, so only use it for short delays.
+pub struct SpinDelay;
+
+impl DelayNs for SpinDelay {
+    fn delay_ns(&mut self, n: u32) {
+        spin_until(Instant::now() + Duration::from_nanos(n.into()));
+    }
+
+    fn delay_us(&mut self, n: u32) {
+        spin_until(Instant::now() + Duration::from_micros(n.into()));
+    }
+
+    fn delay_ms(&mut self, n: u32) {
+        spin_until(Instant::now() + Duration::from_millis(n.into()));
+    }
+}
 
-        thread::sleep(Duration::new(u64(secs), nsecs));
+/// Busy-waits until `deadline`, for delays too short to be honoured by `thread::sleep`
+pub(crate) fn spin_until(deadline: Instant) {
+    while Instant::now() < deadline {
+        hint::spin_loop();
     }
 }
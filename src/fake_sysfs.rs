@@ -0,0 +1,140 @@
+//! Fake sysfs GPIO interface for testing code using [`SysfsPin`](crate::SysfsPin)
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Temporary directory laid out like the sysfs GPIO interface
+///
+/// Pins created with [`SysfsPin::with_root`] or [`SysfsPin::export_with_root`] on
+/// [`FakeSysfs::root`] read and write the files of the fake interface, so that tests can
+/// check the values written by the code under test and inject input values.
+///
+/// Unlike the kernel, the fake interface does not react to writes: pins must be added with
+/// [`FakeSysfs::add_pin`] to appear exported, and the numbers written to the `export` and
+/// `unexport` files are only recorded there. Edge detection is not supported, so waiting for
+/// edges fails. The directory is removed when the `FakeSysfs` is dropped.
+///
+/// ```
+/// use embedded_hal::digital::{InputPin, OutputPin};
+/// use linux_embedded_hal::sysfs_gpio::Direction;
+/// use linux_embedded_hal::{FakeSysfs, SysfsPin};
+///
+/// let sysfs = FakeSysfs::new()?;
+/// sysfs.add_pin(17)?;
+/// sysfs.add_pin(27)?;
+///
+/// let mut led = SysfsPin::export_with_root(sysfs.root(), 17, Direction::Low)?;
+/// led.set_high()?;
+/// assert_eq!(sysfs.attribute(17, "direction")?, "low");
+/// assert!(sysfs.value(17)?);
+///
+/// let mut button = SysfsPin::with_root(sysfs.root(), 27).into_input_pin()?;
+/// sysfs.set_value(27, true)?;
+/// assert!(button.is_high()?);
+///
+/// // Pins that were already exported are left exported.
+/// drop(led);
+/// assert_eq!(sysfs.root_file("unexport")?, "");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`SysfsPin::with_root`]: crate::SysfsPin::with_root
+/// [`SysfsPin::export_with_root`]: crate::SysfsPin::export_with_root
+#[derive(Debug)]
+pub struct FakeSysfs {
+    root: PathBuf,
+}
+
+impl FakeSysfs {
+    /// Creates an empty fake interface in a new temporary directory.
+    pub fn new() -> io::Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let root = std::env::temp_dir().join(format!(
+            "linux-embedded-hal-sysfs-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir(&root)?;
+        let sysfs = FakeSysfs { root };
+        fs::write(sysfs.root.join("export"), "")?;
+        fs::write(sysfs.root.join("unexport"), "")?;
+        Ok(sysfs)
+    }
+
+    /// The directory to use as the root of pins
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Adds an exported pin, as an input with low value, active-high and edge detection
+    /// disabled.
+    pub fn add_pin(&self, pin_num: u64) -> io::Result<()> {
+        fs::create_dir(self.pin_dir(pin_num))?;
+        for (name, contents) in [
+            ("direction", "in"),
+            ("value", "0"),
+            ("active_low", "0"),
+            ("edge", "none"),
+        ] {
+            self.set_attribute(pin_num, name, contents)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a pin, as if it was unexported.
+    pub fn remove_pin(&self, pin_num: u64) -> io::Result<()> {
+        fs::remove_dir_all(self.pin_dir(pin_num))
+    }
+
+    /// The logical value of a pin, as last written or injected
+    pub fn value(&self, pin_num: u64) -> io::Result<bool> {
+        match self.attribute(pin_num, "value")?.as_str() {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("value file contents {}", other),
+            )),
+        }
+    }
+
+    /// Injects the logical value of a pin.
+    pub fn set_value(&self, pin_num: u64, value: bool) -> io::Result<()> {
+        self.set_attribute(pin_num, "value", if value { "1" } else { "0" })
+    }
+
+    /// The contents of the attribute file `name` of a pin, such as `direction`, without
+    /// trailing whitespace
+    pub fn attribute(&self, pin_num: u64, name: &str) -> io::Result<String> {
+        read_trimmed(&self.pin_dir(pin_num).join(name))
+    }
+
+    /// Sets the contents of the attribute file `name` of a pin, such as `active_low`.
+    pub fn set_attribute(&self, pin_num: u64, name: &str, contents: &str) -> io::Result<()> {
+        fs::write(self.pin_dir(pin_num).join(name), format!("{}\n", contents))
+    }
+
+    /// The contents of the file `name` at the root of the interface, such as `export`,
+    /// without trailing whitespace
+    pub fn root_file(&self, name: &str) -> io::Result<String> {
+        read_trimmed(&self.root.join(name))
+    }
+
+    fn pin_dir(&self, pin_num: u64) -> PathBuf {
+        self.root.join(format!("gpio{}", pin_num))
+    }
+}
+
+impl Drop for FakeSysfs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn read_trimmed(path: &Path) -> io::Result<String> {
+    Ok(fs::read_to_string(path)?.trim_end().to_string())
+}
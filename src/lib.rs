@@ -23,34 +23,89 @@ pub use spidev;
 pub use sysfs_gpio;
 
 #[cfg(feature = "gpio_cdev")]
-pub use gpio_cdev;
+pub use gpiocdev;
 #[cfg(feature = "gpio_sysfs")]
 /// Sysfs Pin wrapper module
 mod sysfs_pin;
 
+#[cfg(feature = "gpio_sysfs")]
+/// Fake sysfs GPIO interface module
+mod fake_sysfs;
+
 #[cfg(feature = "gpio_cdev")]
 /// Cdev Pin wrapper module
 mod cdev_pin;
 
+#[cfg(feature = "gpio_cdev")]
+/// Cdev pin group wrapper module
+mod cdev_pin_group;
+
+#[cfg(feature = "gpio_cdev")]
+/// Cdev chip discovery and monitoring module
+mod cdev_chip;
+
+#[cfg(feature = "gpio_cdev")]
+/// Software PWM module
+mod soft_pwm;
+
+#[cfg(feature = "gpio_cdev")]
+/// Pulse measurement module
+mod pulse_meter;
+
+#[cfg(feature = "gpio_cdev")]
+/// Quadrature encoder module
+mod quadrature;
+
+#[cfg(feature = "gpio_cdev")]
+/// Cdev chip re-export
+pub use cdev_chip::{sysfs_gpio_to_cdev, CdevChip, CdevLineWatcher};
 #[cfg(feature = "gpio_cdev")]
 /// Cdev pin re-export
-pub use cdev_pin::{CdevPin, CdevPinError};
+pub use cdev_pin::{
+    CdevEdgeEvent, CdevEdgeEvents, CdevPin, CdevPinBuilder, CdevPinBuilderError, CdevPinError,
+};
+#[cfg(feature = "gpio_cdev")]
+/// Cdev pin group re-export
+pub use cdev_pin_group::{CdevGroupPin, CdevPinGroup};
+#[cfg(feature = "gpio_cdev")]
+/// Pulse measurement re-export
+pub use pulse_meter::{PulseMeasurement, PulseMeter};
+#[cfg(feature = "gpio_cdev")]
+/// Quadrature encoder re-export
+pub use quadrature::{QuadratureDeltas, QuadratureEncoder};
+#[cfg(feature = "gpio_cdev")]
+/// Software PWM re-export
+pub use soft_pwm::{SoftPwm, SoftPwmError};
 
+#[cfg(feature = "gpio_sysfs")]
+/// Fake sysfs GPIO interface re-export
+pub use fake_sysfs::FakeSysfs;
 #[cfg(feature = "gpio_sysfs")]
 /// Sysfs pin re-export
 pub use sysfs_pin::{SysfsPin, SysfsPinError};
 
+#[cfg(all(
+    any(feature = "async-tokio", feature = "async-io"),
+    any(feature = "gpio_cdev", feature = "gpio_sysfs")
+))]
+mod async_fd;
+mod bitbang_i2c;
+mod bitbang_spi;
 mod delay;
 #[cfg(feature = "i2c")]
 mod i2c;
+mod onewire;
 mod serial;
 #[cfg(feature = "spi")]
 mod spi;
 mod timer;
 
-pub use crate::delay::Delay;
+pub use crate::bitbang_i2c::{BitbangI2c, BitbangI2cError};
+pub use crate::bitbang_spi::{BitOrder, BitbangSpi, BitbangSpiError};
+pub use crate::delay::{Delay, SpinDelay};
 #[cfg(feature = "i2c")]
 pub use crate::i2c::{I2CError, I2cdev};
+pub use crate::onewire::{onewire_crc8, OneWire, OneWireBus};
 pub use crate::serial::{Serial, SerialError};
 #[cfg(feature = "spi")]
 pub use crate::spi::{SPIError, Spidev};
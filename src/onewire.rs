@@ -0,0 +1,373 @@
+//! Dallas 1-Wire bus master bit-banged over a GPIO pin
+//!
+//! Timings follow the standard speed values recommended in Maxim application note 126.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+
+use crate::SpinDelay;
+
+/// ROM command addressing every device on the bus at once
+const SKIP_ROM: u8 = 0xCC;
+/// ROM command addressing the device with the ROM code that follows
+const MATCH_ROM: u8 = 0x55;
+/// ROM command starting a search for the ROM codes of the devices on the bus
+const SEARCH_ROM: u8 = 0xF0;
+
+/// A 1-Wire bus master
+///
+/// Drivers for 1-Wire devices can be written against this trait, which only requires the
+/// bit-level operations to be implemented. Bytes are transferred least significant bit first,
+/// and ROM codes are handled as `u64` with the family code in the least significant byte, as
+/// they go on the wire.
+pub trait OneWire {
+    /// Error returned by the bus operations
+    type Error;
+
+    /// Sends a reset pulse and returns whether any device answered with a presence pulse.
+    fn reset(&mut self) -> Result<bool, Self::Error>;
+
+    /// Writes a single bit.
+    fn write_bit(&mut self, bit: bool) -> Result<(), Self::Error>;
+
+    /// Reads a single bit.
+    fn read_bit(&mut self) -> Result<bool, Self::Error>;
+
+    /// Writes a byte, least significant bit first.
+    fn write_byte(&mut self, byte: u8) -> Result<(), Self::Error> {
+        for i in 0..8 {
+            self.write_bit(byte >> i & 1 == 1)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a byte, least significant bit first.
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        let mut byte = 0;
+        for i in 0..8 {
+            byte |= u8::from(self.read_bit()?) << i;
+        }
+        Ok(byte)
+    }
+
+    /// Writes all of `bytes`.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        for byte in bytes {
+            self.write_byte(*byte)?;
+        }
+        Ok(())
+    }
+
+    /// Fills `bytes` with bytes read from the bus.
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        for byte in bytes.iter_mut() {
+            *byte = self.read_byte()?;
+        }
+        Ok(())
+    }
+
+    /// Resets the bus and addresses all devices with a Skip ROM command.
+    ///
+    /// Returns whether any device is present. Only use this to talk to a single device, or
+    /// to send a command all devices can execute at once.
+    fn skip_rom(&mut self) -> Result<bool, Self::Error> {
+        if !self.reset()? {
+            return Ok(false);
+        }
+        self.write_byte(SKIP_ROM)?;
+        Ok(true)
+    }
+
+    /// Resets the bus and addresses the device with the given ROM code with a Match ROM
+    /// command.
+    ///
+    /// Returns whether any device is present.
+    fn match_rom(&mut self, rom: u64) -> Result<bool, Self::Error> {
+        if !self.reset()? {
+            return Ok(false);
+        }
+        self.write_byte(MATCH_ROM)?;
+        self.write_bytes(&rom.to_le_bytes())?;
+        Ok(true)
+    }
+
+    /// Returns the ROM codes of all the devices on the bus, with the Search ROM command.
+    ///
+    /// The ROM codes are not validated, use [`onewire_crc8`] to detect codes corrupted by
+    /// noise on the bus.
+    fn search(&mut self) -> Result<Vec<u64>, Self::Error> {
+        let mut roms = Vec::new();
+        let mut rom = 0_u64;
+        // Position of the last bit where the previous pass took the 0 branch with devices
+        // also on the 1 branch, counted from 1, with 0 meaning there is none left.
+        let mut last_discrepancy = 0;
+        loop {
+            if !self.reset()? {
+                break;
+            }
+            self.write_byte(SEARCH_ROM)?;
+
+            let mut last_zero = 0;
+            for position in 1..=64 {
+                let bit = self.read_bit()?;
+                let complement = self.read_bit()?;
+                if bit && complement {
+                    // All devices left the search, for instance because they were unplugged.
+                    return Ok(roms);
+                }
+                let mask = 1_u64 << (position - 1);
+                let direction = if bit != complement {
+                    bit
+                } else {
+                    let direction = if position < last_discrepancy {
+                        rom & mask != 0
+                    } else {
+                        position == last_discrepancy
+                    };
+                    if !direction {
+                        last_zero = position;
+                    }
+                    direction
+                };
+                if direction {
+                    rom |= mask;
+                } else {
+                    rom &= !mask;
+                }
+                self.write_bit(direction)?;
+            }
+            roms.push(rom);
+
+            last_discrepancy = last_zero;
+            if last_discrepancy == 0 {
+                break;
+            }
+        }
+        Ok(roms)
+    }
+}
+
+/// Computes the Dallas/Maxim CRC-8 used by 1-Wire devices.
+///
+/// The last byte of ROM codes and of most device memories is the CRC of the bytes before it,
+/// so the CRC of a complete ROM code, as in `onewire_crc8(&rom.to_le_bytes())`, is zero when
+/// it is valid.
+pub fn onewire_crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0;
+    for byte in bytes {
+        let mut byte = *byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 1;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+    }
+    crc
+}
+
+/// 1-Wire bus master bit-banged over an open-drain GPIO pin
+///
+/// The pin must be configured as an open-drain output that can be read back, with a pull-up
+/// resistor on the line, such as a [`CdevPin`] built with
+/// [`Drive::OpenDrain`](https://docs.rs/gpiocdev/0.8.0/gpiocdev/line/enum.Drive.html).
+///
+/// Time slots last tens of microseconds and must not be interrupted for more than a few
+/// microseconds, so preemption by other threads can corrupt transfers. Drivers should retry
+/// on CRC errors, and a real-time scheduling policy helps on busy systems. For the same
+/// reason the bus should be timed with a busy-waiting delay such as [`SpinDelay`], as
+/// [`Delay`](crate::Delay) sleeps for much longer than the requested microseconds.
+///
+/// ```no_run
+/// use embedded_hal::digital::PinState;
+/// use linux_embedded_hal::gpiocdev::line::Drive;
+/// use linux_embedded_hal::{onewire_crc8, CdevPin, OneWire, OneWireBus, SpinDelay};
+///
+/// let pin = CdevPin::builder("/dev/gpiochip0", 4)
+///     .output(PinState::High)
+///     .drive(Drive::OpenDrain)
+///     .build()?;
+/// let mut bus = OneWireBus::new(pin, SpinDelay)?;
+/// for rom in bus.search()? {
+///     if onewire_crc8(&rom.to_le_bytes()) == 0 {
+///         println!("found device {:016x}", rom);
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`CdevPin`]: crate::CdevPin
+/// [`SpinDelay`]: crate::SpinDelay
+pub struct OneWireBus<P, D = SpinDelay> {
+    pin: P,
+    delay: D,
+}
+
+impl<P, D, E> OneWireBus<P, D>
+where
+    P: OutputPin<Error = E> + InputPin<Error = E>,
+    D: DelayNs,
+{
+    /// Creates a bus on `pin`, timed with `delay`, and releases the line.
+    pub fn new(mut pin: P, delay: D) -> Result<Self, E> {
+        pin.set_high()?;
+        Ok(OneWireBus { pin, delay })
+    }
+
+    /// Releases the pin and the delay.
+    pub fn release(self) -> (P, D) {
+        (self.pin, self.delay)
+    }
+}
+
+impl<P, D, E> OneWire for OneWireBus<P, D>
+where
+    P: OutputPin<Error = E> + InputPin<Error = E>,
+    D: DelayNs,
+{
+    type Error = E;
+
+    fn reset(&mut self) -> Result<bool, E> {
+        self.pin.set_low()?;
+        self.delay.delay_us(480);
+        self.pin.set_high()?;
+        self.delay.delay_us(70);
+        let present = self.pin.is_low()?;
+        self.delay.delay_us(410);
+        Ok(present)
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), E> {
+        self.pin.set_low()?;
+        if bit {
+            self.delay.delay_us(6);
+            self.pin.set_high()?;
+            self.delay.delay_us(64);
+        } else {
+            self.delay.delay_us(60);
+            self.pin.set_high()?;
+            self.delay.delay_us(10);
+        }
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool, E> {
+        self.pin.set_low()?;
+        self.delay.delay_us(6);
+        self.pin.set_high()?;
+        self.delay.delay_us(9);
+        let bit = self.pin.is_high()?;
+        self.delay.delay_us(55);
+        Ok(bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    /// Devices taking part in a ROM search, simulated at the bit level
+    struct SearchBus {
+        devices: Vec<u64>,
+        active: Vec<bool>,
+        command: Vec<bool>,
+        position: u32,
+        reads: u8,
+    }
+
+    impl SearchBus {
+        fn new(devices: &[u64]) -> Self {
+            SearchBus {
+                devices: devices.to_vec(),
+                active: Vec::new(),
+                command: Vec::new(),
+                position: 0,
+                reads: 0,
+            }
+        }
+
+        fn searching(&self) -> bool {
+            self.command.len() == 8
+        }
+    }
+
+    impl OneWire for SearchBus {
+        type Error = Infallible;
+
+        fn reset(&mut self) -> Result<bool, Infallible> {
+            self.active = vec![true; self.devices.len()];
+            self.command.clear();
+            self.position = 0;
+            self.reads = 0;
+            Ok(!self.devices.is_empty())
+        }
+
+        fn write_bit(&mut self, bit: bool) -> Result<(), Infallible> {
+            if !self.searching() {
+                self.command.push(bit);
+                if self.searching() {
+                    let command = self
+                        .command
+                        .iter()
+                        .rev()
+                        .fold(0, |b, bit| b << 1 | *bit as u8);
+                    assert_eq!(command, SEARCH_ROM);
+                }
+                return Ok(());
+            }
+            assert_eq!(self.reads, 2);
+            for (device, active) in self.devices.iter().zip(self.active.iter_mut()) {
+                if (device >> self.position & 1 == 1) != bit {
+                    *active = false;
+                }
+            }
+            self.position += 1;
+            self.reads = 0;
+            Ok(())
+        }
+
+        fn read_bit(&mut self) -> Result<bool, Infallible> {
+            assert!(self.searching());
+            let complement = self.reads == 1;
+            self.reads += 1;
+            // Devices pull the line low to send a 0, so the bus reads as the AND of all bits.
+            Ok(self
+                .devices
+                .iter()
+                .zip(&self.active)
+                .filter(|(_, active)| **active)
+                .all(|(device, _)| (device >> self.position & 1 == 1) != complement))
+        }
+    }
+
+    #[test]
+    fn search_finds_all_devices() {
+        let mut devices = [
+            0x5A00_0000_0123_4528,
+            0xE200_0000_0123_4528,
+            0x0300_0008_0F2A_CD10,
+            0x0300_0008_0F2A_CD11,
+        ];
+        let mut bus = SearchBus::new(&devices);
+        let mut found = bus.search().unwrap();
+        devices.sort_unstable();
+        found.sort_unstable();
+        assert_eq!(found, devices);
+    }
+
+    #[test]
+    fn search_without_devices_finds_nothing() {
+        assert!(SearchBus::new(&[]).search().unwrap().is_empty());
+    }
+
+    #[test]
+    fn crc8_matches_rom_code() {
+        // Example ROM code from Maxim application note 27.
+        let rom = [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2];
+        assert_eq!(onewire_crc8(&rom[..7]), 0xA2);
+        assert_eq!(onewire_crc8(&rom), 0);
+    }
+}
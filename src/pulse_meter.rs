@@ -0,0 +1,190 @@
+//! Pulse width and frequency measurement on a Linux CDev pin
+
+use std::time::{Duration, Instant};
+
+use gpiocdev::line::{EdgeDetection, EdgeKind, EventClock};
+
+use crate::CdevPin;
+
+/// Measures the frequency and duty cycle of the signal on a [`CdevPin`]
+///
+/// Edges are timestamped by the kernel as they occur, so measurements are not affected by
+/// the latency of reading them from userspace. Edges can still be missed if the signal is too
+/// fast for the GPIO controller or the kernel event queue, which shows up as a longer period.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use linux_embedded_hal::{CdevPin, PulseMeter};
+///
+/// let tach = CdevPin::new_input("/dev/gpiochip0", 23, None)?;
+/// let mut meter = PulseMeter::new(tach, Duration::from_millis(500))?;
+/// if let Some(pulses) = meter.measure()? {
+///     println!("fan speed: {:.0} rpm", pulses.frequency() * 60.0 / 2.0);
+/// }
+/// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+/// ```
+pub struct PulseMeter {
+    pin: CdevPin,
+    window: Duration,
+}
+
+impl PulseMeter {
+    /// Enables edge detection on `pin` and measures over `window` long periods.
+    ///
+    /// The pin is reconfigured as an input if necessary. The window should span at least
+    /// two periods of the slowest signal to be measured.
+    pub fn new(mut pin: CdevPin, window: Duration) -> Result<Self, gpiocdev::Error> {
+        pin.edge_events(EdgeDetection::BothEdges, EventClock::Monotonic)?;
+        Ok(PulseMeter { pin, window })
+    }
+
+    /// The duration of each measurement
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Changes the duration of each measurement.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    /// Releases the pin, leaving edge detection enabled.
+    pub fn release(self) -> CdevPin {
+        self.pin
+    }
+
+    /// Records the edges of the signal for the measurement window and averages them.
+    ///
+    /// Blocks for the whole window. Returns `None` if fewer than two rising edges occurred,
+    /// for instance when the signal is constant.
+    pub fn measure(&mut self) -> Result<Option<PulseMeasurement>, gpiocdev::Error> {
+        // Discard the edges that occurred before the measurement.
+        while self.pin.has_edge_event()? {
+            self.pin.read_edge_event()?;
+        }
+
+        let mut edges = Vec::new();
+        let deadline = Instant::now() + self.window;
+        loop {
+            let now = Instant::now();
+            if now >= deadline || !self.pin.request().wait_edge_event(deadline - now)? {
+                break;
+            }
+            let event = self.pin.read_edge_event()?;
+            edges.push((event.kind, event.timestamp_ns));
+        }
+        Ok(PulseMeasurement::from_edges(&edges))
+    }
+}
+
+/// Averages of a periodic signal over a measurement window, as returned by
+/// [`PulseMeter::measure`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PulseMeasurement {
+    /// Number of complete periods, from rising edge to rising edge, in the window
+    pub cycles: u32,
+    /// Average duration of a period
+    pub period: Duration,
+    /// Average time the signal stays high in a period
+    pub high_time: Duration,
+}
+
+impl PulseMeasurement {
+    /// Average frequency, in Hz
+    pub fn frequency(&self) -> f64 {
+        1.0 / self.period.as_secs_f64()
+    }
+
+    /// Average time the signal stays low in a period
+    pub fn low_time(&self) -> Duration {
+        self.period.saturating_sub(self.high_time)
+    }
+
+    /// Fraction of the period the signal stays high, between 0 and 1
+    pub fn duty_cycle(&self) -> f64 {
+        self.high_time.as_secs_f64() / self.period.as_secs_f64()
+    }
+
+    /// Averages `edges`, given as kinds and timestamps in nanoseconds in order of occurrence.
+    fn from_edges(edges: &[(EdgeKind, u64)]) -> Option<Self> {
+        let rising: Vec<usize> = edges
+            .iter()
+            .enumerate()
+            .filter(|(_, (kind, _))| *kind == EdgeKind::Rising)
+            .map(|(i, _)| i)
+            .collect();
+        if rising.len() < 2 {
+            return None;
+        }
+
+        let (first, last) = (rising[0], rising[rising.len() - 1]);
+        let cycles = rising.len() as u64 - 1;
+        let period = (edges[last].1 - edges[first].1) / cycles;
+
+        // Average the high time over the periods where the falling edge was not missed.
+        let (high_sum, high_count) = rising
+            .windows(2)
+            .filter_map(|cycle| {
+                let (start, end) = (cycle[0], cycle[1]);
+                edges[start + 1..end]
+                    .iter()
+                    .find(|(kind, _)| *kind == EdgeKind::Falling)
+                    .map(|(_, timestamp)| timestamp - edges[start].1)
+            })
+            .fold((0, 0), |(sum, count), high| (sum + high, count + 1));
+        let high_time = high_sum.checked_div(high_count).unwrap_or(0);
+
+        Some(PulseMeasurement {
+            cycles: cycles as u32,
+            period: Duration::from_nanos(period),
+            high_time: Duration::from_nanos(high_time),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EdgeKind::{Falling, Rising};
+
+    #[test]
+    fn averages_complete_periods() {
+        let edges = [
+            (Falling, 500),
+            (Rising, 1_000),
+            (Falling, 1_250),
+            (Rising, 2_000),
+            (Falling, 2_260),
+            (Rising, 3_000),
+            (Falling, 3_240),
+        ];
+        let pulses = PulseMeasurement::from_edges(&edges).unwrap();
+        assert_eq!(pulses.cycles, 2);
+        assert_eq!(pulses.period, Duration::from_nanos(1_000));
+        assert_eq!(pulses.high_time, Duration::from_nanos(255));
+        assert_eq!(pulses.low_time(), Duration::from_nanos(745));
+        assert_eq!(pulses.frequency(), 1e6);
+        assert_eq!(pulses.duty_cycle(), 0.255);
+    }
+
+    #[test]
+    fn skips_missed_falling_edges() {
+        let edges = [
+            (Rising, 1_000),
+            (Rising, 2_000),
+            (Falling, 2_500),
+            (Rising, 3_000),
+        ];
+        let pulses = PulseMeasurement::from_edges(&edges).unwrap();
+        assert_eq!(pulses.period, Duration::from_nanos(1_000));
+        assert_eq!(pulses.high_time, Duration::from_nanos(500));
+    }
+
+    #[test]
+    fn needs_a_complete_period() {
+        assert_eq!(
+            PulseMeasurement::from_edges(&[(Rising, 1_000), (Falling, 1_500)]),
+            None
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Quadrature rotary encoder decoder on two Linux CDev pins
+
+use std::convert::TryFrom;
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+use embedded_hal::digital::InputPin;
+use gpiocdev::line::{EdgeDetection, EdgeKind, EventClock};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::time::{clock_gettime, ClockId};
+
+use crate::{CdevEdgeEvent, CdevPin};
+
+/// Quadrature rotary encoder reading the A and B channels from two [`CdevPin`]s
+///
+/// Every edge on either channel moves the position by one count, so most mechanical
+/// encoders advance by four counts per detent. The position increases when channel A leads
+/// channel B. Edges that do not lead to the next or previous state of the Gray code, which
+/// happens when an edge was missed, leave the position unchanged and are counted in
+/// [`QuadratureEncoder::invalid_transitions`].
+///
+/// Edge detection is done by the kernel, which queues the edges until they are read, so no
+/// movement is lost between calls as long as the kernel event buffers do not overflow.
+///
+/// ```no_run
+/// use linux_embedded_hal::{CdevPin, QuadratureEncoder};
+///
+/// let a = CdevPin::new_input("/dev/gpiochip0", 5, None)?;
+/// let b = CdevPin::new_input("/dev/gpiochip0", 6, None)?;
+/// let mut knob = QuadratureEncoder::new(a, b)?;
+/// for delta in knob.deltas() {
+///     println!("moved by {}", delta?);
+/// }
+/// # Ok::<(), linux_embedded_hal::gpiocdev::Error>(())
+/// ```
+pub struct QuadratureEncoder {
+    a: CdevPin,
+    b: CdevPin,
+    decoder: Decoder,
+}
+
+/// The quadrature state machine
+#[derive(Debug, Default)]
+struct Decoder {
+    state: u8,
+    position: i64,
+    invalid_transitions: u64,
+    /// Edges read after the cutoff of the last update, in timestamp order
+    pending: Vec<Edge>,
+}
+
+/// An edge on the channel with the given state bit, timestamped in nanoseconds
+type Edge = (u64, u8, EdgeKind);
+
+impl QuadratureEncoder {
+    /// Creates an encoder on the `a` and `b` channels, starting at position zero.
+    ///
+    /// The pins are reconfigured as inputs with edge detection on both edges if necessary.
+    pub fn new(mut a: CdevPin, mut b: CdevPin) -> Result<Self, gpiocdev::Error> {
+        let mut state = 0;
+        for (pin, bit) in [(&mut a, A), (&mut b, B)] {
+            pin.edge_events(EdgeDetection::BothEdges, EventClock::Monotonic)?;
+            while pin.has_edge_event()? {
+                pin.read_edge_event()?;
+            }
+            if pin.is_high().map_err(|err| err.inner().clone())? {
+                state |= bit;
+            }
+        }
+        Ok(QuadratureEncoder {
+            a,
+            b,
+            decoder: Decoder {
+                state,
+                ..Decoder::default()
+            },
+        })
+    }
+
+    /// The current position, in counts
+    ///
+    /// Only reflects the edges processed so far by [`QuadratureEncoder::update`] and the
+    /// waiting methods.
+    pub fn position(&self) -> i64 {
+        self.decoder.position
+    }
+
+    /// Changes the current position, for instance to zero it at a reference point.
+    pub fn set_position(&mut self, position: i64) {
+        self.decoder.position = position;
+    }
+
+    /// The number of invalid transitions detected since the encoder was created
+    pub fn invalid_transitions(&self) -> u64 {
+        self.decoder.invalid_transitions
+    }
+
+    /// Releases the A and B pins, leaving edge detection enabled.
+    pub fn release(self) -> (CdevPin, CdevPin) {
+        (self.a, self.b)
+    }
+
+    /// Processes the edges queued so far without blocking and returns the resulting change
+    /// of position.
+    ///
+    /// Edges occurring while the call runs may be held back until the next call, so that the
+    /// edges of both channels are always processed in the order they occurred.
+    pub fn update(&mut self) -> Result<i64, gpiocdev::Error> {
+        self.decoder.update(&self.a, &self.b)
+    }
+
+    /// Blocks until the position changes or `timeout` elapses, and returns the change of
+    /// position.
+    ///
+    /// Returns zero on timeout.
+    pub fn wait_delta(&mut self, timeout: Duration) -> Result<i64, gpiocdev::Error> {
+        self.wait(Instant::now().checked_add(timeout))
+    }
+
+    /// Returns a blocking iterator over the non-zero changes of position.
+    pub fn deltas(&mut self) -> QuadratureDeltas<'_> {
+        QuadratureDeltas { encoder: self }
+    }
+
+    /// Processes edges until the position changes or `deadline` passes, if any.
+    fn wait(&mut self, deadline: Option<Instant>) -> Result<i64, gpiocdev::Error> {
+        loop {
+            let delta = self.update()?;
+            if delta != 0 {
+                return Ok(delta);
+            }
+            if self.decoder.has_pending() {
+                continue;
+            }
+            let timeout_ms = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(0);
+                    }
+                    // Round up so that the wait does not end in a busy loop.
+                    let remaining = (deadline - now)
+                        .checked_add(Duration::from_nanos(999_999))
+                        .map_or(u128::MAX, |remaining| remaining.as_millis());
+                    i32::try_from(remaining).unwrap_or(i32::MAX)
+                }
+                None => -1,
+            };
+            let mut fds = [
+                PollFd::new(self.a.request().as_raw_fd(), PollFlags::POLLIN),
+                PollFd::new(self.b.request().as_raw_fd(), PollFlags::POLLIN),
+            ];
+            match poll(&mut fds, timeout_ms) {
+                Ok(_) | Err(nix::errno::Errno::EINTR) => {}
+                Err(err) => return Err(std::io::Error::from(err).into()),
+            }
+        }
+    }
+
+    /// Waits until the position changes and returns the change of position.
+    #[cfg(any(feature = "async-tokio", feature = "async-io"))]
+    pub async fn next_delta(&mut self) -> Result<i64, gpiocdev::Error> {
+        use crate::async_fd::AsyncFd;
+        use std::future::{poll_fn, Future};
+        use std::os::unix::io::AsFd;
+        use std::task::Poll;
+
+        let a = AsyncFd::new(self.a.request().as_fd())?;
+        let b = AsyncFd::new(self.b.request().as_fd())?;
+        loop {
+            let delta = self.decoder.update(&self.a, &self.b)?;
+            if delta != 0 {
+                return Ok(delta);
+            }
+            if self.decoder.has_pending() {
+                continue;
+            }
+            let mut a_readable = std::pin::pin!(a.readable());
+            let mut b_readable = std::pin::pin!(b.readable());
+            poll_fn(
+                |cx| match (a_readable.as_mut().poll(cx), b_readable.as_mut().poll(cx)) {
+                    (Poll::Ready(Err(err)), _) | (_, Poll::Ready(Err(err))) => {
+                        Poll::Ready(Err(err))
+                    }
+                    (Poll::Ready(Ok(())), _) | (_, Poll::Ready(Ok(()))) => Poll::Ready(Ok(())),
+                    _ => Poll::Pending,
+                },
+            )
+            .await?;
+        }
+    }
+}
+
+/// Blocking iterator over the changes of position of a [`QuadratureEncoder`]
+///
+/// Returned by [`QuadratureEncoder::deltas`].
+pub struct QuadratureDeltas<'a> {
+    encoder: &'a mut QuadratureEncoder,
+}
+
+impl Iterator for QuadratureDeltas<'_> {
+    type Item = Result<i64, gpiocdev::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.encoder.wait(None))
+    }
+}
+
+impl Decoder {
+    /// Applies the edges queued on the `a` and `b` channels and returns the change of
+    /// position.
+    fn update(&mut self, a: &CdevPin, b: &CdevPin) -> Result<i64, gpiocdev::Error> {
+        // The channels are drained one after the other, so an edge on A occurring while B is
+        // drained is only read by the next update. Edges after the cutoff are held back until
+        // then, so that they are applied in order with such edges.
+        let cutoff = monotonic_now()?;
+        let a = read_queued_events(a)?;
+        let b = read_queued_events(b)?;
+        Ok(self.merge(
+            a.into_iter()
+                .map(|event| (event.timestamp_ns, A, event.kind)),
+            b.into_iter()
+                .map(|event| (event.timestamp_ns, B, event.kind)),
+            cutoff,
+        ))
+    }
+
+    /// Applies the pending and new edges up to `cutoff` in timestamp order, keeps the later
+    /// ones pending and returns the change of position.
+    ///
+    /// Both channels are timestamped with the same clock, so they can be merged in order.
+    fn merge<I, J>(&mut self, a: I, b: J, cutoff: u64) -> i64
+    where
+        I: IntoIterator<Item = Edge>,
+        J: IntoIterator<Item = Edge>,
+    {
+        let mut edges = std::mem::take(&mut self.pending);
+        edges.extend(a);
+        edges.extend(b);
+        edges.sort_by_key(|(timestamp, _, _)| *timestamp);
+        let later = edges.partition_point(|(timestamp, _, _)| *timestamp <= cutoff);
+        self.pending = edges.split_off(later);
+        edges
+            .into_iter()
+            .map(|(_, bit, kind)| self.apply(bit, kind))
+            .sum()
+    }
+
+    /// Whether edges are held back for the next update
+    fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Applies an edge on the channel with state bit `bit` and returns the change of
+    /// position.
+    fn apply(&mut self, bit: u8, kind: EdgeKind) -> i64 {
+        let state = match kind {
+            EdgeKind::Rising => self.state | bit,
+            EdgeKind::Falling => self.state & !bit,
+        };
+        // An edge leaving the state unchanged means that the opposite edge was missed.
+        let delta = match step(self.state, state) {
+            Some(0) | None => {
+                self.invalid_transitions += 1;
+                0
+            }
+            Some(delta) => delta,
+        };
+        self.state = state;
+        self.position += delta;
+        delta
+    }
+}
+
+/// State bit of channel A
+const A: u8 = 0b10;
+/// State bit of channel B
+const B: u8 = 0b01;
+
+/// The channel states in the order they go through when the position increases
+const SEQUENCE: [u8; 4] = [0b00, A, A | B, B];
+
+/// The change of position for a transition between two states, or `None` if the transition
+/// is invalid.
+fn step(from: u8, to: u8) -> Option<i64> {
+    let index = |state| SEQUENCE.iter().position(|s| *s == state).unwrap_or(0);
+    match (index(to) + 4 - index(from)) % 4 {
+        0 => Some(0),
+        1 => Some(1),
+        3 => Some(-1),
+        _ => None,
+    }
+}
+
+/// The current time of `CLOCK_MONOTONIC`, which timestamps the edge events, in nanoseconds
+fn monotonic_now() -> Result<u64, gpiocdev::Error> {
+    let now = clock_gettime(ClockId::CLOCK_MONOTONIC).map_err(std::io::Error::from)?;
+    Ok(now.tv_sec() as u64 * 1_000_000_000 + now.tv_nsec() as u64)
+}
+
+fn read_queued_events(pin: &CdevPin) -> Result<Vec<CdevEdgeEvent>, gpiocdev::Error> {
+    let mut events = Vec::new();
+    while pin.has_edge_event()? {
+        events.push(pin.read_edge_event()?);
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_follow_gray_code() {
+        for i in 0..4 {
+            let (state, next) = (SEQUENCE[i], SEQUENCE[(i + 1) % 4]);
+            assert_eq!(step(state, next), Some(1));
+            assert_eq!(step(next, state), Some(-1));
+            assert_eq!(step(state, state), Some(0));
+        }
+    }
+
+    #[test]
+    fn decoder_counts_both_directions() {
+        use EdgeKind::{Falling, Rising};
+
+        let mut decoder = Decoder::default();
+        let forward = [(A, Rising), (B, Rising), (A, Falling), (B, Falling)];
+        let backward = [(B, Rising), (A, Rising), (B, Falling), (A, Falling)];
+        for (bit, kind) in forward.iter().chain(&forward).chain(&backward) {
+            decoder.apply(*bit, *kind);
+        }
+        assert_eq!(decoder.position, 4);
+        assert_eq!(decoder.state, 0);
+        assert_eq!(decoder.invalid_transitions, 0);
+    }
+
+    #[test]
+    fn decoder_rejects_repeated_edges() {
+        use EdgeKind::{Falling, Rising};
+
+        let mut decoder = Decoder::default();
+        assert_eq!(decoder.apply(A, Rising), 1);
+        // The falling edge of B was missed before its rising edge.
+        assert_eq!(decoder.apply(B, Falling), 0);
+        assert_eq!(decoder.invalid_transitions, 1);
+        assert_eq!(decoder.apply(B, Rising), 1);
+        assert_eq!(decoder.position, 2);
+    }
+
+    #[test]
+    fn later_edges_wait_for_the_next_update() {
+        use EdgeKind::{Falling, Rising};
+
+        let mut decoder = Decoder::default();
+        // A falls at 30 after A was drained, while B is drained with its fall at 40.
+        let delta = decoder.merge(
+            vec![(10, A, Rising)],
+            vec![(20, B, Rising), (40, B, Falling)],
+            25,
+        );
+        assert_eq!(delta, 2);
+        assert!(decoder.has_pending());
+        let delta = decoder.merge(vec![(30, A, Falling)], vec![], 50);
+        assert_eq!(delta, 2);
+        assert!(!decoder.has_pending());
+        assert_eq!(decoder.position, 4);
+        assert_eq!(decoder.state, 0);
+        assert_eq!(decoder.invalid_transitions, 0);
+    }
+
+    #[test]
+    fn skipped_states_are_invalid() {
+        assert_eq!(step(0b00, A | B), None);
+        assert_eq!(step(A | B, 0b00), None);
+        assert_eq!(step(A, B), None);
+        assert_eq!(step(B, A), None);
+    }
+}
@@ -0,0 +1,329 @@
+//! Implementation of [`embedded-hal`] PWM traits in software on a Linux CDev pin
+//!
+//! [`embedded-hal`]: https://docs.rs/embedded-hal
+
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use embedded_hal::digital::{OutputPin, PinState};
+
+use crate::{CdevPin, CdevPinError};
+
+/// Software PWM output driving a [`CdevPin`] from a dedicated thread
+///
+/// The duty cycle is the fraction of each period during which the line is physically high.
+/// Timing relies on the thread scheduler, so expect jitter in the order of tens of
+/// microseconds, more on a loaded system. It suits buzzers, LED dimming and other loads
+/// tolerant of an imprecise waveform.
+///
+/// Dropping the PWM stops it and drives the line low. Use [`SoftPwm::stop`] to choose the
+/// final level and get the pin back.
+///
+/// ```no_run
+/// use embedded_hal::pwm::SetDutyCycle;
+/// use linux_embedded_hal::{CdevPin, SoftPwm};
+///
+/// let pin = CdevPin::new_input("/dev/gpiochip0", 18, None)?;
+/// let mut buzzer = SoftPwm::new(pin, 2_000)?;
+/// buzzer.set_duty_cycle_percent(50)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct SoftPwm {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<CdevPin>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+struct State {
+    period: Duration,
+    duty: u16,
+    stop: bool,
+    error: Option<CdevPinError>,
+}
+
+impl SoftPwm {
+    /// Starts a PWM output on `pin` at `frequency` Hz, initially with a duty cycle of zero.
+    ///
+    /// The pin is switched to an output if necessary. Returns an
+    /// [`InvalidArgument`][0] error if `frequency` is zero.
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/enum.Error.html#variant.InvalidArgument
+    pub fn new(mut pin: CdevPin, frequency: u32) -> Result<Self, gpiocdev::Error> {
+        let period = frequency_to_period(frequency)?;
+        pin.set_as_output(PinState::Low)?;
+        pin.set_low().map_err(|err| err.inner().clone())?;
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                period,
+                duty: 0,
+                stop: false,
+                error: None,
+            }),
+            changed: Condvar::new(),
+        });
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("soft-pwm".to_string())
+                .spawn(move || run(pin, &shared))?
+        };
+        Ok(SoftPwm {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// The current PWM period
+    pub fn period(&self) -> Duration {
+        self.shared.lock().period
+    }
+
+    /// Changes the PWM frequency, in Hz, keeping the duty cycle.
+    ///
+    /// The new frequency applies from the next period on. Returns an
+    /// [`InvalidArgument`][0] error if `frequency` is zero.
+    ///
+    /// [0]: https://docs.rs/gpiocdev/0.8.0/gpiocdev/enum.Error.html#variant.InvalidArgument
+    pub fn set_frequency(&mut self, frequency: u32) -> Result<(), gpiocdev::Error> {
+        let period = frequency_to_period(frequency)?;
+        self.shared.update(|state| state.period = period);
+        Ok(())
+    }
+
+    /// Stops the PWM, drives the line to `state` and returns the pin.
+    ///
+    /// Returns the error that stopped the PWM thread instead, if any.
+    pub fn stop(mut self, state: PinState) -> Result<CdevPin, SoftPwmError> {
+        let mut pin = self.join()?;
+        pin.set_state(state)?;
+        Ok(pin)
+    }
+
+    /// Stops the thread and returns the pin, or the error that stopped the thread early.
+    fn join(&mut self) -> Result<CdevPin, SoftPwmError> {
+        self.shared.update(|state| state.stop = true);
+        let pin = self
+            .thread
+            .take()
+            .expect("PWM thread already stopped")
+            .join()
+            .expect("PWM thread panicked");
+        self.shared.check()?;
+        Ok(pin)
+    }
+}
+
+impl Drop for SoftPwm {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            if let Ok(mut pin) = self.join() {
+                let _ = pin.set_low();
+            }
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("PWM state poisoned")
+    }
+
+    /// Returns the error that stopped the PWM thread, if any.
+    ///
+    /// The error is kept, so every later call reports it again.
+    fn check(&self) -> Result<(), SoftPwmError> {
+        match &self.lock().error {
+            Some(err) => Err(err.clone().into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies `f` to the state and wakes up the PWM thread.
+    fn update<F: FnOnce(&mut State)>(&self, f: F) {
+        f(&mut self.lock());
+        self.changed.notify_one();
+    }
+}
+
+/// The PWM thread, returning the pin once stopped
+fn run(mut pin: CdevPin, shared: &Shared) -> CdevPin {
+    let mut state = shared.lock();
+    let mut next = Instant::now();
+    'run: while !state.stop {
+        let (period, duty) = (state.period, state.duty);
+        let high = high_time(period, duty);
+        let low = period - high;
+
+        // Fully on or off, hold the level until the settings change.
+        if high.is_zero() || low.is_zero() {
+            let level = PinState::from(low.is_zero());
+            if let Err(err) = pin.set_state(level) {
+                state.error = Some(err);
+                break;
+            }
+            state = shared
+                .changed
+                .wait_while(state, |state| {
+                    !state.stop && state.period == period && state.duty == duty
+                })
+                .expect("PWM state poisoned");
+            next = Instant::now();
+            continue;
+        }
+
+        // Start over instead of rushing through missed periods after a scheduling delay.
+        let now = Instant::now();
+        if now > next + period {
+            next = now;
+        }
+        for (level, time) in [(PinState::High, high), (PinState::Low, low)] {
+            if let Err(err) = pin.set_state(level) {
+                state.error = Some(err);
+                break 'run;
+            }
+            next += time;
+            while !state.stop {
+                let now = Instant::now();
+                if now >= next {
+                    break;
+                }
+                state = shared
+                    .changed
+                    .wait_timeout(state, next - now)
+                    .expect("PWM state poisoned")
+                    .0;
+            }
+            if state.stop {
+                break 'run;
+            }
+        }
+    }
+    pin
+}
+
+/// The part of `period` during which the line is high for `duty`
+fn high_time(period: Duration, duty: u16) -> Duration {
+    let nanos = period.as_nanos() * u128::from(duty) / u128::from(u16::MAX);
+    Duration::from_nanos(nanos as u64)
+}
+
+fn frequency_to_period(frequency: u32) -> Result<Duration, gpiocdev::Error> {
+    if frequency == 0 {
+        return Err(gpiocdev::Error::InvalidArgument(
+            "PWM frequency must not be zero".to_string(),
+        ));
+    }
+    Ok(Duration::from_secs(1) / frequency)
+}
+
+/// Error type wrapping [`CdevPinError`] to implement [`embedded_hal::pwm::Error`]
+#[derive(Debug)]
+pub struct SoftPwmError {
+    err: CdevPinError,
+}
+
+impl SoftPwmError {
+    /// Fetch inner (concrete) [`CdevPinError`]
+    pub fn inner(&self) -> &CdevPinError {
+        &self.err
+    }
+}
+
+impl From<CdevPinError> for SoftPwmError {
+    fn from(err: CdevPinError) -> Self {
+        Self { err }
+    }
+}
+
+impl fmt::Display for SoftPwmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.err)
+    }
+}
+
+impl std::error::Error for SoftPwmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+impl embedded_hal::pwm::Error for SoftPwmError {
+    fn kind(&self) -> embedded_hal::pwm::ErrorKind {
+        use embedded_hal::pwm::ErrorKind;
+        ErrorKind::Other
+    }
+}
+
+impl embedded_hal::pwm::ErrorType for SoftPwm {
+    type Error = SoftPwmError;
+}
+
+impl embedded_hal::pwm::SetDutyCycle for SoftPwm {
+    fn max_duty_cycle(&self) -> u16 {
+        u16::MAX
+    }
+
+    /// Changes the duty cycle from the next period on.
+    ///
+    /// Returns the error that stopped the PWM thread, if any.
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.shared.check()?;
+        self.shared.update(|state| state.duty = duty);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn high_time_scales_with_duty() {
+        let period = Duration::from_millis(1);
+        assert_eq!(high_time(period, 0), Duration::ZERO);
+        assert_eq!(high_time(period, u16::MAX), period);
+        assert_eq!(
+            high_time(period, u16::MAX / 2),
+            Duration::from_nanos(499_992)
+        );
+    }
+
+    #[test]
+    fn thread_error_is_reported_on_every_call() {
+        use embedded_hal::pwm::SetDutyCycle;
+
+        let err = gpiocdev::Error::InvalidArgument("line released".to_string());
+        let mut pwm = SoftPwm {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    period: Duration::from_millis(1),
+                    duty: 0,
+                    stop: false,
+                    error: Some(err.into()),
+                }),
+                changed: Condvar::new(),
+            }),
+            thread: None,
+        };
+        assert!(pwm.set_duty_cycle(1).is_err());
+        assert!(pwm.set_duty_cycle(2).is_err());
+        assert!(pwm.shared.check().is_err());
+        assert_eq!(pwm.shared.lock().duty, 0);
+    }
+
+    #[test]
+    fn zero_frequency_is_rejected() {
+        assert!(frequency_to_period(0).is_err());
+        assert_eq!(
+            frequency_to_period(1_000).unwrap(),
+            Duration::from_millis(1)
+        );
+    }
+}
@@ -11,10 +11,10 @@ use std::path::Path;
 
 /// Newtype around [`spidev::Spidev`] that implements the `embedded-hal` traits
 ///
-/// [Delay operations][delay] are capped to 65535 microseconds.
+/// [Delay operations][delay] are rounded up to whole microseconds and capped to 65535 microseconds.
 ///
 /// [`spidev::Spidev`]: https://docs.rs/spidev/0.5.2/spidev/struct.Spidev.html
-/// [delay]: embedded_hal::spi::Operation::DelayUs
+/// [delay]: embedded_hal::spi::Operation::DelayNs
 pub struct Spidev(pub spidev::Spidev);
 
 impl Spidev {
@@ -100,10 +100,10 @@ mod embedded_hal_impl {
     impl SpiDevice for Spidev {
         /// Perform a transaction against the device. [Read more][transaction]
         ///
-        /// [Delay operations][delay] are capped to 65535 microseconds.
+        /// [Delay operations][delay] are rounded up to whole microseconds and capped to 65535 microseconds.
         ///
         /// [transaction]: SpiDevice::transaction
-        /// [delay]: SpiOperation::DelayUs
+        /// [delay]: SpiOperation::DelayNs
         fn transaction(
             &mut self,
             operations: &mut [SpiOperation<'_, u8>],
@@ -133,8 +133,10 @@ mod embedded_hal_impl {
                         };
                         transfers.push(SpidevTransfer::read_write(tx, buf));
                     }
-                    SpiOperation::DelayUs(us) => {
-                        let us = (*us).try_into().unwrap_or(u16::MAX);
+                    SpiOperation::DelayNs(ns) => {
+                        let us = (*ns / 1000 + u32::from(*ns % 1000 != 0))
+                            .try_into()
+                            .unwrap_or(u16::MAX);
                         transfers.push(SpidevTransfer::delay(us));
                     }
                 }
@@ -2,49 +2,542 @@
 //!
 //! [`embedded-hal`]: https://docs.rs/embedded-hal
 
+use std::convert::TryFrom;
 use std::fmt;
-use std::path::Path;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
-/// Newtype around [`sysfs_gpio::Pin`] that implements the `embedded-hal` traits
+/// Directory of the sysfs GPIO interface
+const SYSFS_GPIO_ROOT: &str = "/sys/class/gpio";
+
+/// How long [`SysfsPin::export`] waits for udev to grant access to an exported pin
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Pin of the sysfs GPIO interface that implements the `embedded-hal` traits
+///
+/// The sysfs specific settings are available through [`SysfsPin::set_direction`],
+/// [`SysfsPin::set_edge`] and [`SysfsPin::set_active_low`], which use the sysfs GPIO interface
+/// of the pin, including for pins created with [`SysfsPin::with_root`].
 ///
-/// [`sysfs_gpio::Pin`]: https://docs.rs/sysfs_gpio/0.6.0/sysfs_gpio/struct.Pin.html
-pub struct SysfsPin(pub sysfs_gpio::Pin);
+/// The `active_low` attribute is cached and the `value` file kept open from the first
+/// [`embedded_hal::digital`] operation on, so that each operation is a single system call.
+/// Call [`SysfsPin::refresh`] after changing the active-low setting by other means or exporting
+/// the pin again.
+pub struct SysfsPin {
+    pin_num: u64,
+    root: PathBuf,
+    unexport_on_drop: bool,
+    active_low: bool,
+    value: Option<File>,
+}
 
 impl SysfsPin {
-    /// See [`sysfs_gpio::Pin::new`][0] for details.
+    /// Creates the pin numbered `pin_num` in the sysfs GPIO interface at `/sys/class/gpio`.
     ///
-    /// [0]: https://docs.rs/sysfs_gpio/0.6.0/sysfs_gpio/struct.Pin.html#method.new
+    /// Nothing is written to sysfs: the pin is not exported, and is left as is when dropped.
+    /// Use [`SysfsPin::export`] to export and configure it as well.
     pub fn new(pin_num: u64) -> Self {
-        SysfsPin(sysfs_gpio::Pin::new(pin_num))
+        Self::with_root(SYSFS_GPIO_ROOT, pin_num)
+    }
+
+    /// Creates a pin of the sysfs GPIO interface found in `root` instead of
+    /// `/sys/class/gpio`.
+    ///
+    /// This is mostly useful to test code against a [`FakeSysfs`](crate::FakeSysfs).
+    pub fn with_root<P>(root: P, pin_num: u64) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        SysfsPin {
+            pin_num,
+            root: root.into(),
+            unexport_on_drop: false,
+            active_low: false,
+            value: None,
+        }
+    }
+
+    /// Exports the pin, sets its `direction` and unexports it when dropped.
+    ///
+    /// A pin that was already exported, for instance by another program, is only configured
+    /// and left exported when dropped. udev rules commonly grant access to the files of newly
+    /// exported pins shortly after the export, so this waits up to one second for the
+    /// `direction` and `value` files to be writable before giving up with a permission error.
+    /// If the pin cannot be configured it is unexported again, unless it was already exported.
+    ///
+    /// Use [`SysfsPin::set_unexport_on_drop`] to leave the pin exported after use.
+    ///
+    /// ```no_run
+    /// use embedded_hal::digital::OutputPin;
+    /// use linux_embedded_hal::sysfs_gpio::Direction;
+    /// use linux_embedded_hal::SysfsPin;
+    ///
+    /// let mut led = SysfsPin::export(21, Direction::Low)?;
+    /// led.set_high()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn export(
+        pin_num: u64,
+        direction: sysfs_gpio::Direction,
+    ) -> Result<Self, sysfs_gpio::Error> {
+        Self::export_with_root(SYSFS_GPIO_ROOT, pin_num, direction)
+    }
+
+    /// Like [`SysfsPin::export`], for the sysfs GPIO interface found in `root`.
+    pub fn export_with_root<P>(
+        root: P,
+        pin_num: u64,
+        direction: sysfs_gpio::Direction,
+    ) -> Result<Self, sysfs_gpio::Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let mut pin = Self::with_root(root, pin_num);
+        if !pin.is_exported() {
+            fs::write(pin.root.join("export"), pin_num.to_string())?;
+            // Also unexports the pin again if the configuration fails.
+            pin.unexport_on_drop = true;
+        }
+        pin.wait_for_access()?;
+        pin.write_direction(direction)?;
+        Ok(pin)
     }
 
     /// See [`sysfs_gpio::Pin::from_path`][0] for details.
     ///
+    /// The pin belongs to the sysfs GPIO interface of the directory containing `path`.
+    ///
     /// [0]: https://docs.rs/sysfs_gpio/0.6.0/sysfs_gpio/struct.Pin.html#method.from_path
     pub fn from_path<P>(path: P) -> sysfs_gpio::Result<Self>
     where
         P: AsRef<Path>,
     {
-        sysfs_gpio::Pin::from_path(path).map(SysfsPin)
+        let path = path.as_ref();
+        let pin = sysfs_gpio::Pin::from_path(path)?;
+        let root = path.parent().unwrap_or_else(|| Path::new(SYSFS_GPIO_ROOT));
+        Ok(Self::with_root(root, pin.get_pin_num()))
+    }
+
+    /// The directory of the sysfs GPIO interface of the pin
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The number of the pin in the sysfs GPIO interface
+    pub fn pin_num(&self) -> u64 {
+        self.pin_num
+    }
+
+    /// Reads the `direction` attribute of the pin.
+    ///
+    /// The kernel reports output pins as [`Out`](sysfs_gpio::Direction::Out), whatever their
+    /// initial level.
+    pub fn direction(&self) -> Result<sysfs_gpio::Direction, sysfs_gpio::Error> {
+        match self.read_attr("direction")?.as_str() {
+            "in" => Ok(sysfs_gpio::Direction::In),
+            "out" => Ok(sysfs_gpio::Direction::Out),
+            other => Err(sysfs_gpio::Error::Unexpected(format!(
+                "direction file contents {}",
+                other
+            ))),
+        }
+    }
+
+    /// Sets the `direction` attribute of the pin.
+    ///
+    /// [`High`](sysfs_gpio::Direction::High) and [`Low`](sysfs_gpio::Direction::Low) switch
+    /// the pin to an output driven to that level without glitching.
+    pub fn set_direction(
+        &mut self,
+        direction: sysfs_gpio::Direction,
+    ) -> Result<(), sysfs_gpio::Error> {
+        self.write_direction(direction)
+    }
+
+    /// Reads the `edge` attribute of the pin.
+    ///
+    /// As in sysfs, edges refer to the logical value of the pin, so rising and falling are
+    /// swapped on active-low pins.
+    pub fn edge(&self) -> Result<sysfs_gpio::Edge, sysfs_gpio::Error> {
+        match self.read_attr("edge")?.as_str() {
+            "none" => Ok(sysfs_gpio::Edge::NoInterrupt),
+            "rising" => Ok(sysfs_gpio::Edge::RisingEdge),
+            "falling" => Ok(sysfs_gpio::Edge::FallingEdge),
+            "both" => Ok(sysfs_gpio::Edge::BothEdges),
+            other => Err(sysfs_gpio::Error::Unexpected(format!(
+                "edge file contents {}",
+                other
+            ))),
+        }
+    }
+
+    /// Sets the `edge` attribute of the pin, with the same logical edges as
+    /// [`SysfsPin::edge`].
+    pub fn set_edge(&self, edge: sysfs_gpio::Edge) -> Result<(), sysfs_gpio::Error> {
+        self.write_attr("edge", edge_name(edge))
+    }
+
+    /// Reads the `active_low` attribute of the pin.
+    pub fn active_low(&self) -> Result<bool, sysfs_gpio::Error> {
+        self.read_active_low()
+    }
+
+    /// Sets the `active_low` attribute of the pin.
+    ///
+    /// Pin states remain physical levels, so this only changes the logical value reported by
+    /// sysfs and the meaning of [`SysfsPin::edge`].
+    pub fn set_active_low(&mut self, active_low: bool) -> Result<(), sysfs_gpio::Error> {
+        self.write_attr("active_low", if active_low { "1" } else { "0" })?;
+        self.active_low = active_low;
+        Ok(())
+    }
+
+    /// Whether the pin is exported, that is whether its directory exists in the sysfs GPIO
+    /// interface
+    pub fn is_exported(&self) -> bool {
+        self.attr_path("").exists()
+    }
+
+    /// Unexports the pin if it is exported.
+    ///
+    /// The pin is then no longer unexported when dropped.
+    pub fn unexport(&mut self) -> Result<(), sysfs_gpio::Error> {
+        self.value = None;
+        self.unexport_on_drop = false;
+        if self.is_exported() {
+            fs::write(self.root.join("unexport"), self.pin_num.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Whether the pin is unexported when dropped
+    ///
+    /// Only pins exported by [`SysfsPin::export`] are unexported by default.
+    pub fn unexport_on_drop(&self) -> bool {
+        self.unexport_on_drop
+    }
+
+    /// Sets whether the pin is unexported when dropped.
+    pub fn set_unexport_on_drop(&mut self, unexport: bool) {
+        self.unexport_on_drop = unexport;
+    }
+
+    /// Reads the `active_low` attribute again and reopens the `value` file.
+    pub fn refresh(&mut self) -> Result<(), sysfs_gpio::Error> {
+        self.value = None;
+        self.active_low = self.read_active_low()?;
+        let value = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(self.attr_path("value"))?;
+        self.value = Some(value);
+        Ok(())
+    }
+
+    /// The cached active-low setting and the open `value` file, refreshed on first use
+    fn value_file(&mut self) -> Result<(bool, &File), sysfs_gpio::Error> {
+        if self.value.is_none() {
+            self.refresh()?;
+        }
+        let value = self.value.as_ref().expect("value file opened by refresh");
+        Ok((self.active_low, value))
+    }
+
+    /// Returns whether the pin is physically high.
+    fn get_level(&mut self) -> Result<bool, sysfs_gpio::Error> {
+        let (active_low, value) = self.value_file()?;
+        Ok(read_value(value)? != active_low)
+    }
+
+    /// Drives the pin physically high or low.
+    fn set_level(&mut self, high: bool) -> Result<(), sysfs_gpio::Error> {
+        let (active_low, value) = self.value_file()?;
+        let contents: &[u8] = if high != active_low { b"1" } else { b"0" };
+        value.write_at(contents, 0)?;
+        Ok(())
     }
 
     /// Convert this pin to an input pin
-    pub fn into_input_pin(self) -> Result<SysfsPin, sysfs_gpio::Error> {
-        self.set_direction(sysfs_gpio::Direction::In)?;
+    pub fn into_input_pin(mut self) -> Result<SysfsPin, sysfs_gpio::Error> {
+        self.write_direction(sysfs_gpio::Direction::In)?;
         Ok(self)
     }
 
     /// Convert this pin to an output pin
     pub fn into_output_pin(
-        self,
+        mut self,
         state: embedded_hal::digital::PinState,
     ) -> Result<SysfsPin, sysfs_gpio::Error> {
-        self.set_direction(match state {
+        self.write_direction(match state {
             embedded_hal::digital::PinState::High => sysfs_gpio::Direction::High,
             embedded_hal::digital::PinState::Low => sysfs_gpio::Direction::Low,
         })?;
         Ok(self)
     }
+
+    /// Writes the `direction` attribute and closes the `value` file, so that the next
+    /// operation reopens it for the new direction.
+    fn write_direction(
+        &mut self,
+        direction: sysfs_gpio::Direction,
+    ) -> Result<(), sysfs_gpio::Error> {
+        self.value = None;
+        self.write_attr("direction", direction_name(direction))
+    }
+
+    /// Blocks until the pin undergoes a transition matching `edge` or `timeout` elapses.
+    ///
+    /// Returns true if an edge was detected and false on timeout. Edges refer to the physical
+    /// line level, like [`InputPin::is_high`], so a rising edge is a transition from low to
+    /// high even on an active-low pin.
+    ///
+    /// The pin `edge` attribute is updated if necessary and left as is after the call
+    /// returns. The pin should be configured as an input. Returns an
+    /// [`Unexpected`](sysfs_gpio::Error::Unexpected) error if `edge` is
+    /// [`NoInterrupt`](sysfs_gpio::Edge::NoInterrupt).
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use linux_embedded_hal::sysfs_gpio::Edge;
+    /// use linux_embedded_hal::SysfsPin;
+    ///
+    /// let drdy = SysfsPin::new(17).into_input_pin()?;
+    /// if !drdy.wait_for_edge(Edge::FallingEdge, Duration::from_millis(200))? {
+    ///     println!("timed out waiting for data");
+    /// }
+    /// # Ok::<(), linux_embedded_hal::sysfs_gpio::Error>(())
+    /// ```
+    ///
+    /// [`InputPin::is_high`]: embedded_hal::digital::InputPin::is_high
+    pub fn wait_for_edge(
+        &self,
+        edge: sysfs_gpio::Edge,
+        timeout: Duration,
+    ) -> Result<bool, sysfs_gpio::Error> {
+        if edge == sysfs_gpio::Edge::NoInterrupt {
+            return Err(sysfs_gpio::Error::Unexpected(
+                "cannot wait for an edge with edge detection disabled".to_string(),
+            ));
+        }
+        self.enable_edge(swap_edge(edge, self.read_active_low()?))?;
+
+        let deadline = Instant::now().checked_add(timeout);
+        let value = ValueFile::open(&self.attr_path("value"))?;
+        value.read()?;
+        loop {
+            let timeout_ms = match deadline {
+                Some(deadline) => {
+                    // Round up so that a short non-zero timeout does not turn into a
+                    // non-blocking poll.
+                    let remaining = deadline
+                        .saturating_duration_since(Instant::now())
+                        .checked_add(Duration::from_nanos(999_999))
+                        .map_or(u128::MAX, |remaining| remaining.as_millis());
+                    i32::try_from(remaining).unwrap_or(i32::MAX)
+                }
+                None => -1,
+            };
+            if value.wait_edge(timeout_ms)? {
+                return Ok(true);
+            }
+            if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Waits for the physical `level` or, without a level, for the physical `edge`.
+    #[cfg(any(feature = "async-tokio", feature = "async-io"))]
+    async fn wait_for(
+        &mut self,
+        level: Option<embedded_hal::digital::PinState>,
+        edge: sysfs_gpio::Edge,
+    ) -> Result<(), sysfs_gpio::Error> {
+        use crate::async_fd::AsyncFd;
+        use std::os::unix::io::AsFd;
+
+        let (active_low, _) = self.value_file()?;
+        self.enable_edge(match level {
+            Some(_) => sysfs_gpio::Edge::BothEdges,
+            None => swap_edge(edge, active_low),
+        })?;
+
+        // Reading the value acknowledges the previous edges, so only later ones are notified.
+        let value = ValueFile::open(&self.attr_path("value"))?;
+        let expected =
+            level.map(|state| (state == embedded_hal::digital::PinState::High) != active_low);
+        let current = value.read()?;
+        if expected == Some(current) {
+            return Ok(());
+        }
+
+        let fd = AsyncFd::new(value.epoll.as_fd())?;
+        loop {
+            fd.readable().await?;
+            if !value.wait_edge(0)? {
+                continue;
+            }
+            let current = value.read()?;
+            if expected.is_none() || expected == Some(current) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Sets the `edge` attribute to `edge` if it differs.
+    fn enable_edge(&self, edge: sysfs_gpio::Edge) -> Result<(), sysfs_gpio::Error> {
+        let name = edge_name(edge);
+        if self.read_attr("edge")? != name {
+            self.write_attr("edge", name)?;
+        }
+        Ok(())
+    }
+
+    fn read_active_low(&self) -> Result<bool, sysfs_gpio::Error> {
+        match self.read_attr("active_low")?.as_str() {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            other => Err(sysfs_gpio::Error::Unexpected(format!(
+                "active_low file contents {}",
+                other
+            ))),
+        }
+    }
+
+    /// Path of the attribute file `name` of the pin
+    fn attr_path(&self, name: &str) -> PathBuf {
+        self.root.join(format!("gpio{}", self.pin_num)).join(name)
+    }
+
+    fn read_attr(&self, name: &str) -> Result<String, sysfs_gpio::Error> {
+        Ok(fs::read_to_string(self.attr_path(name))?.trim().to_string())
+    }
+
+    fn write_attr(&self, name: &str, contents: &str) -> Result<(), sysfs_gpio::Error> {
+        Ok(fs::write(self.attr_path(name), contents)?)
+    }
+
+    /// Waits until the `direction` and `value` files of the pin are writable.
+    fn wait_for_access(&self) -> Result<(), sysfs_gpio::Error> {
+        use nix::unistd::{access, AccessFlags};
+
+        let deadline = Instant::now() + EXPORT_TIMEOUT;
+        for name in ["direction", "value"] {
+            let path = self.attr_path(name);
+            loop {
+                match access(&path, AccessFlags::W_OK) {
+                    Ok(()) => break,
+                    // The file may also not exist yet while the kernel creates the pin
+                    // directory.
+                    Err(nix::errno::Errno::EACCES | nix::errno::Errno::ENOENT)
+                        if Instant::now() < deadline =>
+                    {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                    Err(err) => return Err(io::Error::from(err).into()),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `value` file of an exported pin, monitored for edges
+///
+/// The kernel signals edges on the `value` file with `POLLPRI`, while the file always polls
+/// as readable. As the async reactors only wait for readability, the file is registered with
+/// a dedicated epoll instance for `POLLPRI`, which in turn polls as readable when an edge is
+/// pending.
+struct ValueFile {
+    file: File,
+    epoll: OwnedFd,
+}
+
+impl ValueFile {
+    fn open(path: &Path) -> Result<Self, sysfs_gpio::Error> {
+        use nix::sys::epoll::{
+            epoll_create1, epoll_ctl, EpollCreateFlags, EpollEvent, EpollFlags, EpollOp,
+        };
+
+        let file = File::open(path)?;
+        let epoll = epoll_create1(EpollCreateFlags::EPOLL_CLOEXEC).map_err(io::Error::from)?;
+        // SAFETY: the file descriptor was just created and is not owned by anything else.
+        let epoll = unsafe { OwnedFd::from_raw_fd(epoll) };
+        let mut event = EpollEvent::new(EpollFlags::EPOLLPRI, 0);
+        epoll_ctl(
+            epoll.as_raw_fd(),
+            EpollOp::EpollCtlAdd,
+            file.as_raw_fd(),
+            &mut event,
+        )
+        .map_err(io::Error::from)?;
+        Ok(ValueFile { file, epoll })
+    }
+
+    /// Waits up to `timeout_ms`, or forever if negative, for an edge to occur after the value
+    /// was last read, and returns whether one did.
+    ///
+    /// Also returns false if the wait is interrupted by a signal.
+    fn wait_edge(&self, timeout_ms: i32) -> Result<bool, sysfs_gpio::Error> {
+        let mut events = [nix::sys::epoll::EpollEvent::empty()];
+        match nix::sys::epoll::epoll_wait(self.epoll.as_raw_fd(), &mut events, timeout_ms as isize)
+        {
+            Ok(count) => Ok(count > 0),
+            Err(nix::errno::Errno::EINTR) => Ok(false),
+            Err(err) => Err(io::Error::from(err).into()),
+        }
+    }
+
+    /// Reads the logical value of the pin.
+    fn read(&self) -> Result<bool, sysfs_gpio::Error> {
+        read_value(&self.file)
+    }
+}
+
+/// Reads the logical value of a pin from its `value` file.
+fn read_value(file: &File) -> Result<bool, sysfs_gpio::Error> {
+    let mut buf = [0; 2];
+    let len = file.read_at(&mut buf, 0)?;
+    match &buf[..len] {
+        [b'0', ..] => Ok(false),
+        [b'1', ..] => Ok(true),
+        other => Err(sysfs_gpio::Error::Unexpected(format!(
+            "value file contents {:?}",
+            String::from_utf8_lossy(other)
+        ))),
+    }
+}
+
+fn direction_name(direction: sysfs_gpio::Direction) -> &'static str {
+    match direction {
+        sysfs_gpio::Direction::In => "in",
+        sysfs_gpio::Direction::Out => "out",
+        sysfs_gpio::Direction::High => "high",
+        sysfs_gpio::Direction::Low => "low",
+    }
+}
+
+fn edge_name(edge: sysfs_gpio::Edge) -> &'static str {
+    match edge {
+        sysfs_gpio::Edge::NoInterrupt => "none",
+        sysfs_gpio::Edge::RisingEdge => "rising",
+        sysfs_gpio::Edge::FallingEdge => "falling",
+        sysfs_gpio::Edge::BothEdges => "both",
+    }
+}
+
+/// Converts between physical and logical edges for active-low pins.
+fn swap_edge(edge: sysfs_gpio::Edge, is_active_low: bool) -> sysfs_gpio::Edge {
+    match (edge, is_active_low) {
+        (sysfs_gpio::Edge::RisingEdge, true) => sysfs_gpio::Edge::FallingEdge,
+        (sysfs_gpio::Edge::FallingEdge, true) => sysfs_gpio::Edge::RisingEdge,
+        (edge, _) => edge,
+    }
 }
 
 /// Error type wrapping [sysfs_gpio::Error](sysfs_gpio::Error) to implement [embedded_hal::digital::Error]
@@ -91,52 +584,208 @@ impl embedded_hal::digital::ErrorType for SysfsPin {
 
 impl embedded_hal::digital::OutputPin for SysfsPin {
     fn set_low(&mut self) -> Result<(), Self::Error> {
-        if self.0.get_active_low().map_err(SysfsPinError::from)? {
-            self.0.set_value(1).map_err(SysfsPinError::from)
-        } else {
-            self.0.set_value(0).map_err(SysfsPinError::from)
-        }
+        Ok(self.set_level(false)?)
     }
 
     fn set_high(&mut self) -> Result<(), Self::Error> {
-        if self.0.get_active_low().map_err(SysfsPinError::from)? {
-            self.0.set_value(0).map_err(SysfsPinError::from)
-        } else {
-            self.0.set_value(1).map_err(SysfsPinError::from)
-        }
+        Ok(self.set_level(true)?)
+    }
+}
+
+impl embedded_hal::digital::StatefulOutputPin for SysfsPin {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.get_level()?)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_set_high().map(|val| !val)
     }
 }
 
 impl embedded_hal::digital::InputPin for SysfsPin {
-    fn is_high(&self) -> Result<bool, Self::Error> {
-        if !self.0.get_active_low().map_err(SysfsPinError::from)? {
-            self.0
-                .get_value()
-                .map(|val| val != 0)
-                .map_err(SysfsPinError::from)
-        } else {
-            self.0
-                .get_value()
-                .map(|val| val == 0)
-                .map_err(SysfsPinError::from)
-        }
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.get_level()?)
     }
 
-    fn is_low(&self) -> Result<bool, Self::Error> {
-        self.is_high().map(|val| !val).map_err(SysfsPinError::from)
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|val| !val)
     }
 }
 
-impl core::ops::Deref for SysfsPin {
-    type Target = sysfs_gpio::Pin;
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+impl embedded_hal_async::digital::Wait for SysfsPin {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        Ok(self
+            .wait_for(
+                Some(embedded_hal::digital::PinState::High),
+                sysfs_gpio::Edge::BothEdges,
+            )
+            .await?)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        Ok(self
+            .wait_for(
+                Some(embedded_hal::digital::PinState::Low),
+                sysfs_gpio::Edge::BothEdges,
+            )
+            .await?)
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(self.wait_for(None, sysfs_gpio::Edge::RisingEdge).await?)
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(self.wait_for(None, sysfs_gpio::Edge::FallingEdge).await?)
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(self.wait_for(None, sysfs_gpio::Edge::BothEdges).await?)
+    }
+}
+
+impl Drop for SysfsPin {
+    fn drop(&mut self) {
+        if self.unexport_on_drop {
+            let _ = self.unexport();
+        }
     }
 }
 
-impl core::ops::DerefMut for SysfsPin {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FakeSysfs;
+    use embedded_hal::digital::{InputPin, OutputPin, PinState, StatefulOutputPin};
+
+    /// Exports `pin_num` on `sysfs`, adding the pin once its number is written to `export`
+    /// as the kernel would.
+    fn export(sysfs: &FakeSysfs, pin_num: u64, direction: sysfs_gpio::Direction) -> SysfsPin {
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                while sysfs.root_file("export").unwrap() != pin_num.to_string() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                sysfs.add_pin(pin_num).unwrap();
+            });
+            SysfsPin::export_with_root(sysfs.root(), pin_num, direction).unwrap()
+        })
+    }
+
+    #[test]
+    fn export_configures_and_unexports() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let pin = export(&sysfs, 4, sysfs_gpio::Direction::High);
+        assert!(pin.unexport_on_drop());
+        assert_eq!(sysfs.attribute(4, "direction").unwrap(), "high");
+        drop(pin);
+        assert_eq!(sysfs.root_file("unexport").unwrap(), "4");
+    }
+
+    #[test]
+    fn export_can_leave_pin_exported() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let mut pin = export(&sysfs, 4, sysfs_gpio::Direction::In);
+        pin.set_unexport_on_drop(false);
+        drop(pin);
+        assert_eq!(sysfs.root_file("unexport").unwrap(), "");
+    }
+
+    #[test]
+    fn export_leaves_pre_exported_pin_exported() {
+        let sysfs = FakeSysfs::new().unwrap();
+        sysfs.add_pin(4).unwrap();
+        let pin = SysfsPin::export_with_root(sysfs.root(), 4, sysfs_gpio::Direction::Low).unwrap();
+        assert!(!pin.unexport_on_drop());
+        assert_eq!(sysfs.root_file("export").unwrap(), "");
+        assert_eq!(sysfs.attribute(4, "direction").unwrap(), "low");
+        drop(pin);
+        assert_eq!(sysfs.root_file("unexport").unwrap(), "");
+    }
+
+    #[test]
+    fn unexport_uses_the_pin_root() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let mut pin = SysfsPin::with_root(sysfs.root(), 4);
+        assert!(!pin.is_exported());
+        pin.unexport().unwrap();
+        assert_eq!(sysfs.root_file("unexport").unwrap(), "");
+
+        let mut pin = export(&sysfs, 4, sysfs_gpio::Direction::In);
+        assert!(pin.is_exported());
+        pin.unexport().unwrap();
+        assert!(!pin.unexport_on_drop());
+        assert_eq!(sysfs.root_file("unexport").unwrap(), "4");
+    }
+
+    #[test]
+    fn levels_account_for_active_low() {
+        let sysfs = FakeSysfs::new().unwrap();
+        sysfs.add_pin(9).unwrap();
+        sysfs.set_attribute(9, "active_low", "1").unwrap();
+        let mut pin = SysfsPin::with_root(sysfs.root(), 9)
+            .into_output_pin(PinState::Low)
+            .unwrap();
+        pin.set_high().unwrap();
+        assert!(!sysfs.value(9).unwrap());
+        assert!(pin.is_set_high().unwrap());
+
+        sysfs.set_value(9, true).unwrap();
+        assert!(pin.is_low().unwrap());
+    }
+
+    #[test]
+    fn direction_change_reopens_value() {
+        let sysfs = FakeSysfs::new().unwrap();
+        sysfs.add_pin(9).unwrap();
+        let mut pin = SysfsPin::with_root(sysfs.root(), 9);
+        assert!(pin.is_low().unwrap());
+        assert!(pin.value.is_some());
+
+        let mut pin = pin.into_output_pin(PinState::Low).unwrap();
+        assert!(pin.value.is_none());
+        pin.set_high().unwrap();
+        assert!(sysfs.value(9).unwrap());
+    }
+
+    #[test]
+    fn settings_use_the_pin_root() {
+        let sysfs = FakeSysfs::new().unwrap();
+        sysfs.add_pin(9).unwrap();
+        let mut pin = SysfsPin::with_root(sysfs.root(), 9);
+        assert_eq!(pin.pin_num(), 9);
+
+        pin.set_direction(sysfs_gpio::Direction::High).unwrap();
+        assert_eq!(sysfs.attribute(9, "direction").unwrap(), "high");
+        sysfs.set_attribute(9, "direction", "out").unwrap();
+        assert_eq!(pin.direction().unwrap(), sysfs_gpio::Direction::Out);
+
+        pin.set_edge(sysfs_gpio::Edge::FallingEdge).unwrap();
+        assert_eq!(sysfs.attribute(9, "edge").unwrap(), "falling");
+        assert_eq!(pin.edge().unwrap(), sysfs_gpio::Edge::FallingEdge);
+
+        pin.set_high().unwrap();
+        pin.set_active_low(true).unwrap();
+        assert_eq!(sysfs.attribute(9, "active_low").unwrap(), "1");
+        assert!(pin.active_low().unwrap());
+        pin.set_high().unwrap();
+        assert!(!sysfs.value(9).unwrap());
+    }
+
+    #[test]
+    fn refresh_reloads_active_low() {
+        let sysfs = FakeSysfs::new().unwrap();
+        sysfs.add_pin(9).unwrap();
+        let mut pin = SysfsPin::with_root(sysfs.root(), 9);
+        pin.set_high().unwrap();
+        assert!(sysfs.value(9).unwrap());
+
+        sysfs.set_attribute(9, "active_low", "1").unwrap();
+        pin.set_high().unwrap();
+        assert!(sysfs.value(9).unwrap());
+        pin.refresh().unwrap();
+        pin.set_high().unwrap();
+        assert!(!sysfs.value(9).unwrap());
     }
 }